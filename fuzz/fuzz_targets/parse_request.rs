@@ -0,0 +1,43 @@
+//! Feeds arbitrary bytes to the request parsers, and every accessor of what they parse. Any
+//! panic is a bug: malformed input must end in a `RequestParsingError`.
+//!
+//! Run with `cargo fuzz run parse_request`. The `seed-*` inputs of `corpus/parse_request/` start it
+//! from the adversarial cases: bare CR or LF, unterminated or oversized chunks, truncated heads.
+
+#![no_main]
+
+use http_lib::http::{
+    borrowed::BorrowedRequest, forwarded::IpNetwork, multipart, proxy::ProxyHeader, range, Request,
+    Response,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(request) = Request::parse(data) {
+        let _ = request.host();
+        let _ = request.is_keep_alive();
+        let _ = request.has_body();
+        let _ = request.basic_auth();
+        let _ = request.cookies();
+        let _ = request.accept();
+        let _ = request.multipart();
+        let _ = request.if_none_match();
+        let _ = request.if_range();
+        let _ = request.path();
+        let _ = request.client_ip(&[IpNetwork::parse("0.0.0.0/0").unwrap()]);
+        if let Some(Ok(ranges)) = request.range() {
+            let _ = range::resolve(&ranges, 0);
+            let _ = range::resolve(&ranges, 16);
+        }
+        let _ = Response::ranged(&request, b"fuzz", None, None);
+        let _ = request.write_to(&mut Vec::new());
+    }
+
+    if let Ok(head) = BorrowedRequest::parse(data) {
+        let _ = head.to_owned_request();
+    }
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = ProxyHeader::parse(line);
+    }
+    let _ = multipart::parse(data, "boundary");
+});
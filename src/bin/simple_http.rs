@@ -1,8 +1,11 @@
-use std::net::{TcpListener, TcpStream};
+use std::net::SocketAddr;
 
 use anyhow::Context;
-use http_lib::http::Request;
-use log::{debug, info, log_enabled, Level};
+use http_lib::{
+    http::{Request, Response, StatusCode},
+    server::{Server, ServerConfig},
+};
+use log::info;
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
@@ -12,24 +15,39 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn start_server() -> anyhow::Result<()> {
-    info!("Starting server on port 8080");
-
-    let listener = TcpListener::bind("0.0.0.0:8080").context("Failed to start listener")?;
-
-    for stream in listener.incoming() {
-        handle_connection(stream?)?;
+    // Settings come from `SIMPLE_HTTP_*` environment variables, see `ServerConfig::from_env`
+    let config = ServerConfig::from_env().context("Invalid configuration")?;
+
+    // The bind address can be overridden with the first argument, ie: `simple_http [::1]:3000`,
+    // or `simple_http unix:/run/simple_http.sock` to listen on a Unix socket
+    let server = match std::env::args().nth(1) {
+        #[cfg(unix)]
+        Some(addr) if addr.starts_with("unix:") => {
+            Server::bind_unix(&addr["unix:".len()..], config)
+        }
+        Some(addr) => {
+            let addr: SocketAddr = addr
+                .parse()
+                .with_context(|| format!("Invalid bind address: {}", addr))?;
+            Server::bind(ServerConfig { addr, ..config })
+        }
+        None => Server::bind(config),
     }
+    .context("Failed to start listener")?;
 
+    // Stop accepting connections on SIGINT/SIGTERM, letting in-flight ones complete
+    let shutdown = server.shutdown_handle()?;
+    ctrlc::set_handler(move || {
+        info!("Received termination signal");
+        shutdown.shutdown();
+    })
+    .context("Failed to set signal handler")?;
+
+    server.serve(handle_request)?;
     Ok(())
 }
 
-fn handle_connection(stream: TcpStream) -> anyhow::Result<()> {
-    if log_enabled!(Level::Debug) {
-        let peer_addr = stream.peer_addr().context("Failed to read peer address")?;
-        debug!("Received a new connection from {}", peer_addr)
-    };
-
-    let request = Request::try_from(stream)?;
-    debug!("{:?}", request);
-    todo!()
+fn handle_request(_request: &Request) -> Response {
+    // No routes are served yet
+    Response::new(StatusCode::NotFound)
 }
@@ -0,0 +1,277 @@
+//! Cross-origin resource sharing, letting browser scripts from other origins call a handler.
+
+use std::time::Duration;
+
+use crate::{
+    http::{Method, Request, Response, StatusCode},
+    server::{Handler, HandlerError},
+};
+
+#[derive(Debug, Clone)]
+enum Origins {
+    Any,
+    List(Vec<String>),
+}
+
+/// A [`Handler`] answering CORS preflight requests and adding `Access-Control-Allow-Origin` to
+/// the responses of the handler it wraps, for requests from an allowed `Origin`.
+///
+/// Preflights (`OPTIONS` requests with an `Origin` and `Access-Control-Request-Method`) never
+/// reach the wrapped handler. They are answered with the allowed methods and headers, or with a
+/// `403 Forbidden` if the origin, method or one of the headers isn't allowed. Other requests go
+/// to the wrapped handler, and their response only allows the origin if it is in the config.
+///
+/// No origin is allowed by default, and only `GET`, `HEAD` and `POST` requests.
+pub struct Cors<H> {
+    handler: H,
+    origins: Origins,
+    methods: Vec<Method>,
+    headers: Vec<String>,
+    max_age: Option<Duration>,
+}
+
+impl<H: Handler> Cors<H> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler,
+            origins: Origins::List(Vec::new()),
+            methods: vec![Method::Get, Method::Head, Method::Post],
+            headers: Vec::new(),
+            max_age: None,
+        }
+    }
+
+    /// Allows requests from every origin, answered with `Access-Control-Allow-Origin: *`.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.origins = Origins::Any;
+        self
+    }
+
+    /// Allows requests from `origin`, such as `https://example.com`, which responses reflect.
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        match &mut self.origins {
+            Origins::List(origins) => origins.push(origin.into()),
+            Origins::Any => {}
+        }
+        self
+    }
+
+    /// Replaces the methods cross-origin requests may use.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Replaces the request headers cross-origin requests may send, beyond those browsers
+    /// always allow.
+    pub fn allow_headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// How long browsers may cache a preflight response, sent in `Access-Control-Max-Age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Value of `Access-Control-Allow-Origin` for a request from `origin`, `None` if it isn't
+    /// allowed.
+    fn allowed_origin<'a>(&self, origin: &'a str) -> Option<&'a str> {
+        match &self.origins {
+            Origins::Any => Some("*"),
+            Origins::List(origins) => origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+                .then_some(origin),
+        }
+    }
+
+    fn preflight(&self, origin: &str, request_method: &str, request_headers: &str) -> Response {
+        let method_allowed =
+            Method::try_from(request_method).is_ok_and(|method| self.methods.contains(&method));
+        let headers_allowed = request_headers
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| self.headers.iter().any(|h| h.eq_ignore_ascii_case(name)));
+        let Some(allow_origin) = self
+            .allowed_origin(origin)
+            .filter(|_| method_allowed && headers_allowed)
+        else {
+            return Response::new(StatusCode::Forbidden);
+        };
+
+        let methods: Vec<_> = self.methods.iter().map(ToString::to_string).collect();
+        let mut response = self
+            .with_origin(Response::new(StatusCode::Ok), allow_origin)
+            .with_header("Access-Control-Allow-Methods", methods.join(", "));
+        if !self.headers.is_empty() {
+            response.set_header("Access-Control-Allow-Headers", self.headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            response.set_header("Access-Control-Max-Age", max_age.as_secs().to_string());
+        }
+        response
+    }
+
+    fn with_origin(&self, mut response: Response, allow_origin: &str) -> Response {
+        response.set_header("Access-Control-Allow-Origin", allow_origin);
+        match self.origins {
+            // The response depends on the origin, caches must not serve it to other ones
+            Origins::List(_) => response.with_header("Vary", "Origin"),
+            Origins::Any => response,
+        }
+    }
+}
+
+impl<H: Handler> Handler for Cors<H> {
+    fn handle(&self, request: &Request) -> Result<Response, HandlerError> {
+        let Some(origin) = request.header("Origin") else {
+            return self.handler.handle(request);
+        };
+
+        if request.method() == Method::Options {
+            if let Some(request_method) = request.header("Access-Control-Request-Method") {
+                let request_headers = request
+                    .header("Access-Control-Request-Headers")
+                    .unwrap_or_default();
+                return Ok(self.preflight(origin, request_method, request_headers));
+            }
+        }
+
+        let response = self.handler.handle(request)?;
+        Ok(match self.allowed_origin(origin) {
+            Some(allow_origin) => self.with_origin(response, allow_origin),
+            None => response,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, headers: &str) -> Request {
+        let message = format!(
+            "{} /api HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            method, headers
+        );
+        Request::parse(message.as_bytes()).expect("error parsing request")
+    }
+
+    fn ok(_: &Request) -> Response {
+        Response::new(StatusCode::Ok).with_body("ok")
+    }
+
+    fn cors() -> Cors<fn(&Request) -> Response> {
+        Cors::new(ok as fn(&Request) -> Response)
+            .allow_origin("https://app.example")
+            .allow_methods([Method::Get, Method::Put])
+            .allow_headers(["Content-Type", "X-Token"])
+            .max_age(Duration::from_secs(600))
+    }
+
+    #[test]
+    fn cors_answers_preflight_from_allowed_origin() {
+        let preflight = request(
+            "OPTIONS",
+            "Origin: https://app.example\r\nAccess-Control-Request-Method: PUT\r\n\
+             Access-Control-Request-Headers: content-type, x-token\r\n",
+        );
+
+        let response = cors().handle(&preflight).unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(
+            response.header("Access-Control-Allow-Origin"),
+            Some("https://app.example")
+        );
+        assert_eq!(
+            response.header("Access-Control-Allow-Methods"),
+            Some("GET, PUT")
+        );
+        assert_eq!(
+            response.header("Access-Control-Allow-Headers"),
+            Some("Content-Type, X-Token")
+        );
+        assert_eq!(response.header("Access-Control-Max-Age"), Some("600"));
+        assert_eq!(response.header("Vary"), Some("Origin"));
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn cors_adds_origin_to_actual_response() {
+        let response = cors()
+            .handle(&request("GET", "Origin: https://app.example\r\n"))
+            .unwrap();
+
+        assert_eq!(response.body(), b"ok");
+        assert_eq!(
+            response.header("Access-Control-Allow-Origin"),
+            Some("https://app.example")
+        );
+    }
+
+    #[test]
+    fn cors_rejects_disallowed_origin() {
+        let preflight = request(
+            "OPTIONS",
+            "Origin: https://evil.example\r\nAccess-Control-Request-Method: GET\r\n",
+        );
+        let response = cors().handle(&preflight).unwrap();
+        assert_eq!(response.status(), StatusCode::Forbidden);
+        assert_eq!(response.header("Access-Control-Allow-Origin"), None);
+
+        // The handler still answers, but browsers won't let the script read the response
+        let response = cors()
+            .handle(&request("GET", "Origin: https://evil.example\r\n"))
+            .unwrap();
+        assert_eq!(response.body(), b"ok");
+        assert_eq!(response.header("Access-Control-Allow-Origin"), None);
+    }
+
+    #[test]
+    fn cors_rejects_disallowed_method_and_headers() {
+        for headers in [
+            "Access-Control-Request-Method: DELETE\r\n",
+            "Access-Control-Request-Method: GET\r\nAccess-Control-Request-Headers: X-Other\r\n",
+        ] {
+            let preflight = request(
+                "OPTIONS",
+                &format!("Origin: https://app.example\r\n{}", headers),
+            );
+
+            let response = cors().handle(&preflight).unwrap();
+
+            assert_eq!(response.status(), StatusCode::Forbidden, "{}", headers);
+        }
+    }
+
+    #[test]
+    fn cors_allows_any_origin_with_wildcard() {
+        let cors = Cors::new(ok).allow_any_origin();
+
+        let preflight = request(
+            "OPTIONS",
+            "Origin: https://anywhere.example\r\nAccess-Control-Request-Method: POST\r\n",
+        );
+        let response = cors.handle(&preflight).unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(response.header("Vary"), None);
+
+        let response = cors
+            .handle(&request("GET", "Origin: https://anywhere.example\r\n"))
+            .unwrap();
+        assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+    }
+
+    #[test]
+    fn cors_passes_requests_without_origin_through() {
+        let response = cors().handle(&request("OPTIONS", "")).unwrap();
+
+        assert_eq!(response.body(), b"ok");
+        assert_eq!(response.header("Access-Control-Allow-Origin"), None);
+    }
+}
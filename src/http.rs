@@ -1,28 +1,308 @@
 use std::{
     error::Error,
     fmt::Display,
-    io::{self, Read},
-    net::TcpStream,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    str::Utf8Error,
 };
 
 use reader::{RequestReader, RequestReaderError};
 
+pub mod auth;
+mod base64;
+pub mod body;
+pub mod borrowed;
+mod chunked;
+pub mod client;
+pub mod coding;
+pub mod conditional;
+pub mod cookie;
+pub mod date;
+pub mod encoding;
+pub mod extensions;
+pub mod forwarded;
+pub mod headers;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod media;
+pub mod multipart;
+pub mod proxy;
+pub mod range;
 pub mod reader;
+pub mod response;
+pub mod target;
+pub mod websocket;
+
+pub use extensions::Extensions;
+pub use headers::Headers;
+pub use response::{Response, StatusCode};
+
+/// Largest body accepted when no explicit limit is configured.
+pub const DEFAULT_MAX_BODY_SIZE: u64 = 1024 * 1024;
 
 // TODO: Split Format error into multiple errors / be more descriptive
 #[derive(Debug)]
 pub enum RequestParsingError {
     Io(io::Error),
+    /// The connection ended cleanly before any byte of a request was received. This is how
+    /// clients close keep-alive connections, or health checks probe the port, and isn't an error
+    /// worth reporting.
+    ConnectionClosed,
     Format,
+    /// The request line isn't made of fields separated by single spaces (RFC 7230 §3.1.1), such
+    /// as with a tab or a doubled space. `reason` describes what was wrong.
+    InvalidStartLine {
+        reason: &'static str,
+    },
     UnsupportedVersion(Version),
+    BodyTooLarge {
+        limit: u64,
+    },
+    /// A chunk of a chunked body declared a size larger than `limit`.
+    ChunkTooLarge {
+        limit: u64,
+    },
+    /// The connection ended after part of the request head was received, before the empty line
+    /// ending it.
+    IncompleteHead,
+    /// The connection ended before the `Content-Length` declared by the request was read.
+    IncompleteBody {
+        expected: u64,
+        received: u64,
+    },
+    /// The request wasn't fully read before its deadline.
+    Timeout,
+    /// The request has more headers than the reader's limit.
+    TooManyHeaders {
+        limit: usize,
+    },
+    /// The start line, in practice the request target, is longer than the reader's limit.
+    UriTooLong {
+        limit: usize,
+    },
+    /// A header line is longer than the reader's limit.
+    HeaderLineTooLong {
+        limit: usize,
+    },
+    /// A CR (`b'\r'`) or LF (`b'\n'`) outside of a CRLF line ending, in the start line or a
+    /// header line. Recipients disagreeing on whether it ends the line could be made to see
+    /// different requests (RFC 7230 §3.5), so it is rejected rather than kept in the value.
+    BareLineEnding(u8),
+    /// A header name that isn't a valid token, such as one containing a space.
+    InvalidHeaderName(String),
+    /// The value of the named header contains control characters.
+    InvalidHeaderValue(String),
+    /// The request target contains characters that aren't allowed in a URI, such as control
+    /// characters.
+    InvalidTarget(String),
+    /// The connection didn't start with a valid PROXY protocol line.
+    InvalidProxyHeader,
+    /// The first byte of a request can't start a method, such as the `0x16` of a TLS
+    /// handshake sent to a plaintext port.
+    NotHttp(u8),
+    /// An HTTP/1.1 request without a `Host` header.
+    MissingHost,
+    /// A request with more than one `Host` header.
+    DuplicateHost,
+    /// Part of the request isn't valid UTF-8, from byte `valid_up_to` of its line. `part` is
+    /// `None` for lines read outside of request parsing, such as a PROXY protocol header.
+    InvalidEncoding {
+        part: Option<MessagePart>,
+        valid_up_to: usize,
+    },
+    /// A `Content-Length` that isn't a number, or multiple conflicting ones.
+    InvalidContentLength,
+    /// A `Transfer-Encoding` coding the server doesn't know.
+    UnsupportedTransferCoding(String),
+    /// A `Transfer-Encoding` that doesn't end with `chunked`, or repeats it, so the end of the
+    /// body can't be found.
+    InvalidTransferEncoding,
+    /// The client asked for `100-continue` but declared a body larger than `limit`.
+    ExpectationFailed {
+        limit: u64,
+    },
+    /// A chunked body sent the named trailer field without listing it in its `Trailer` header.
+    UndeclaredTrailer(String),
 }
 
 impl Display for RequestParsingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Io(e) => write!(f, "IO error parsing request: {}", e),
+            Self::ConnectionClosed => write!(f, "connection closed before a request was sent"),
             Self::Format => write!(f, "unexpected format while parsing request"),
-            Self::UnsupportedVersion(v) => write!(f, "unsupported HTTP version: {}", v),
+            Self::InvalidStartLine { reason } => write!(f, "malformed request line: {}", reason),
+            Self::UnsupportedVersion(v) => {
+                let supported: Vec<_> =
+                    Version::SUPPORTED.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "unsupported HTTP version: {} (supported versions: {})",
+                    v,
+                    supported.join(", ")
+                )
+            }
+            Self::BodyTooLarge { limit } => {
+                write!(f, "request body exceeds the limit of {} bytes", limit)
+            }
+            Self::ChunkTooLarge { limit } => {
+                write!(f, "body chunk exceeds the limit of {} bytes", limit)
+            }
+            Self::IncompleteHead => {
+                write!(f, "connection closed in the middle of the request head")
+            }
+            Self::IncompleteBody { expected, received } => write!(
+                f,
+                "connection closed after {} of {} body bytes",
+                received, expected
+            ),
+            Self::Timeout => write!(f, "timed out reading the request"),
+            Self::TooManyHeaders { limit } => {
+                write!(f, "request has more than {} headers", limit)
+            }
+            Self::UriTooLong { limit } => {
+                write!(f, "request line is longer than {} bytes", limit)
+            }
+            Self::HeaderLineTooLong { limit } => {
+                write!(f, "header line is longer than {} bytes", limit)
+            }
+            Self::BareLineEnding(b'\r') => write!(f, "bare CR without LF in the request head"),
+            Self::BareLineEnding(_) => write!(f, "bare LF without CR in the request head"),
+            Self::InvalidHeaderName(name) => write!(f, "invalid header name {:?}", name),
+            Self::InvalidHeaderValue(name) => {
+                write!(f, "invalid characters in the value of header {}", name)
+            }
+            Self::InvalidTarget(target) => write!(f, "invalid characters in target {:?}", target),
+            Self::InvalidProxyHeader => write!(f, "invalid PROXY protocol header"),
+            Self::NotHttp(byte) => write!(f, "not an HTTP request, starts with byte {:#04x}", byte),
+            Self::MissingHost => write!(f, "missing Host header"),
+            Self::DuplicateHost => write!(f, "multiple Host headers"),
+            Self::UnsupportedTransferCoding(coding) => {
+                write!(f, "unsupported transfer coding {:?}", coding)
+            }
+            Self::InvalidEncoding { part, valid_up_to } => {
+                let part = match part {
+                    Some(MessagePart::StartLine) => "start line",
+                    Some(MessagePart::Headers) => "headers",
+                    Some(MessagePart::Body) => "body framing",
+                    None => "request",
+                };
+                write!(
+                    f,
+                    "invalid UTF-8 in the {} after byte {}",
+                    part, valid_up_to
+                )
+            }
+            Self::InvalidContentLength => write!(f, "invalid Content-Length"),
+            Self::InvalidTransferEncoding => {
+                write!(f, "transfer codings must end with a single chunked")
+            }
+            Self::ExpectationFailed { limit } => write!(
+                f,
+                "refusing 100-continue, declared body exceeds the limit of {} bytes",
+                limit
+            ),
+            Self::UndeclaredTrailer(name) => {
+                write!(f, "trailer field {} missing from the Trailer header", name)
+            }
+        }
+    }
+}
+
+impl RequestParsingError {
+    /// Status code of the response that should be sent to the client for this error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Io(_)
+            | Self::ConnectionClosed
+            | Self::Format
+            | Self::InvalidStartLine { .. }
+            | Self::IncompleteHead
+            | Self::IncompleteBody { .. }
+            | Self::BareLineEnding(_)
+            | Self::InvalidHeaderName(_)
+            | Self::InvalidHeaderValue(_)
+            | Self::InvalidTarget(_)
+            | Self::InvalidProxyHeader
+            | Self::NotHttp(_)
+            | Self::MissingHost
+            | Self::DuplicateHost
+            | Self::InvalidEncoding { .. }
+            | Self::InvalidContentLength
+            | Self::InvalidTransferEncoding
+            | Self::UndeclaredTrailer(_) => StatusCode::BadRequest,
+            Self::UnsupportedTransferCoding(_) => StatusCode::NotImplemented,
+            Self::Timeout => StatusCode::RequestTimeout,
+            Self::TooManyHeaders { .. } | Self::HeaderLineTooLong { .. } => {
+                StatusCode::RequestHeaderFieldsTooLarge
+            }
+            Self::UriTooLong { .. } => StatusCode::UriTooLong,
+            Self::UnsupportedVersion(_) => StatusCode::HttpVersionNotSupported,
+            Self::BodyTooLarge { .. } | Self::ChunkTooLarge { .. } => StatusCode::PayloadTooLarge,
+            Self::ExpectationFailed { .. } => StatusCode::ExpectationFailed,
+        }
+    }
+
+    /// Versions that would have been accepted, for [`RequestParsingError::UnsupportedVersion`].
+    pub fn supported_versions(&self) -> Option<&'static [Version]> {
+        match self {
+            Self::UnsupportedVersion(_) => Some(&Version::SUPPORTED),
+            _ => None,
+        }
+    }
+
+    /// Kind of the IO error that interrupted reading the request, if any. Timeouts, which have
+    /// their own variant, are reported as [`io::ErrorKind::TimedOut`].
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Self::Io(e) => Some(e.kind()),
+            Self::Timeout => Some(io::ErrorKind::TimedOut),
+            _ => None,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout)
+    }
+
+    /// Whether the connection ended partway through the request, in its head or its body. A
+    /// connection closed before the request started is [`RequestParsingError::ConnectionClosed`]
+    /// instead.
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(self, Self::IncompleteHead | Self::IncompleteBody { .. })
+            || self.io_error_kind() == Some(io::ErrorKind::UnexpectedEof)
+    }
+}
+
+impl From<io::Error> for RequestParsingError {
+    fn from(value: io::Error) -> Self {
+        match value.kind() {
+            // Socket read timeouts are reported as `WouldBlock` on some platforms
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Self::Timeout,
+            _ => Self::Io(value),
+        }
+    }
+}
+
+/// Section of a request, to locate parsing errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessagePart {
+    StartLine,
+    Headers,
+    /// Chunk size lines and trailers of a chunked body.
+    Body,
+}
+
+impl RequestParsingError {
+    /// Converts errors from reading `part` of a request, keeping track of where an encoding error
+    /// happened.
+    pub(crate) fn reading(part: MessagePart) -> impl Fn(RequestReaderError) -> Self {
+        move |e| match e {
+            RequestReaderError::Encoding(e) => Self::InvalidEncoding {
+                part: Some(part),
+                valid_up_to: e.utf8_error().valid_up_to(),
+            },
+            e => e.into(),
         }
     }
 }
@@ -30,8 +310,14 @@ impl Display for RequestParsingError {
 impl From<RequestReaderError> for RequestParsingError {
     fn from(value: RequestReaderError) -> Self {
         match value {
-            RequestReaderError::Io(e) => Self::Io(e),
-            RequestReaderError::Encoding(_) => Self::Format,
+            RequestReaderError::Io(e) => e.into(),
+            RequestReaderError::Encoding(e) => Self::InvalidEncoding {
+                part: None,
+                valid_up_to: e.utf8_error().valid_up_to(),
+            },
+            RequestReaderError::TooManyHeaders { limit } => Self::TooManyHeaders { limit },
+            RequestReaderError::StartLineTooLong { limit } => Self::UriTooLong { limit },
+            RequestReaderError::HeaderLineTooLong { limit } => Self::HeaderLineTooLong { limit },
         }
     }
 }
@@ -68,6 +354,35 @@ impl TryFrom<&str> for Method {
     }
 }
 
+impl Method {
+    /// Safe methods are read-only, and don't change the state of the server (RFC 7231 §4.2.1).
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Self::Get | Self::Head | Self::Options | Self::Trace)
+    }
+
+    /// Idempotent methods have the same effect whether a request is sent once or repeated, so
+    /// they can be retried automatically (RFC 7231 §4.2.2).
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self, Self::Put | Self::Delete)
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let method = match self {
+            Self::Get => "GET",
+            Self::Head => "HEAD",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Delete => "DELETE",
+            Self::Connect => "CONNECT",
+            Self::Options => "OPTIONS",
+            Self::Trace => "TRACE",
+        };
+        write!(f, "{}", method)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Version {
     V0_9,
@@ -78,8 +393,11 @@ pub enum Version {
 }
 
 impl Version {
+    /// Versions the server can handle requests for.
+    pub const SUPPORTED: [Version; 2] = [Self::V1, Self::V1_1];
+
     pub fn is_supported(&self) -> bool {
-        matches!(self, Self::V1 | Self::V1_1)
+        Self::SUPPORTED.contains(self)
     }
 }
 
@@ -89,7 +407,7 @@ impl TryFrom<&str> for Version {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "HTTP/0.9" => Ok(Self::V0_9),
-            "HTTP/1" => Ok(Self::V1),
+            "HTTP/1.0" | "HTTP/1" => Ok(Self::V1),
             "HTTP/1.1" => Ok(Self::V1_1),
             "HTTP/2" => Ok(Self::V2),
             "HTTP/3" => Ok(Self::V3),
@@ -102,7 +420,7 @@ impl Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::V0_9 => write!(f, "HTTP/0.9"),
-            Self::V1 => write!(f, "HTTP/1"),
+            Self::V1 => write!(f, "HTTP/1.0"),
             Self::V1_1 => write!(f, "HTTP/1.1"),
             Self::V2 => write!(f, "HTTP/2"),
             Self::V3 => write!(f, "HTTP/3"),
@@ -115,64 +433,549 @@ pub struct Request {
     method: Method,
     target: String,
     version: Version,
-    headers: Vec<String>,
-    body: Option<String>,
+    headers: Headers,
+    body: Option<Vec<u8>>,
+    trailers: Headers,
+    peer_addr: Option<SocketAddr>,
+    extensions: Extensions,
 }
 
 impl Request {
-    fn try_from_reader<R: Read>(
-        reader: &mut RequestReader<R>,
-    ) -> Result<Self, RequestParsingError> {
-        let start_line = reader.read_start_line().expect("Error reading start line");
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Replaces the request target, such as to strip a path prefix before passing the request
+    /// on. The new target is checked like a parsed one, leaving the request unchanged if it is
+    /// invalid. [`Request::path`], [`Request::query`] and the other components then follow it.
+    pub fn set_target(&mut self, target: impl Into<String>) -> Result<(), RequestParsingError> {
+        let target = target.into();
+        validate_target(self.method, &target)?;
+        self.target = target;
+        Ok(())
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Each distinct header name once, see [`Headers::names`].
+    pub fn header_names(&self) -> impl Iterator<Item = &str> {
+        self.headers.names()
+    }
+
+    /// Raw body, as read from the connection.
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    /// Body as a string, erroring if it isn't valid UTF-8.
+    pub fn body_str(&self) -> Option<Result<&str, Utf8Error>> {
+        self.body_bytes().map(str::from_utf8)
+    }
+
+    /// Trailer fields sent after a chunked body. Empty for other requests.
+    pub fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    /// Address of the client that sent the request, if it was read from a socket.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Returns the value of the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Data attached to the request while it is handled.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Takes the request apart into its method, target, version, headers and body, such as to
+    /// modify one of them and rebuild it with [`Request::from_parts`]. Trailers, the peer address
+    /// and extensions are dropped.
+    pub fn into_parts(self) -> (Method, String, Version, Headers, Option<Vec<u8>>) {
+        (
+            self.method,
+            self.target,
+            self.version,
+            self.headers,
+            self.body,
+        )
+    }
+
+    /// Builds a request from the parts returned by [`Request::into_parts`], without trailers,
+    /// peer address or extensions.
+    pub fn from_parts(
+        method: Method,
+        target: String,
+        version: Version,
+        headers: Headers,
+        body: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            method,
+            target,
+            version,
+            headers,
+            body,
+            trailers: Headers::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Host the request is for. The authority of an absolute-form target takes precedence over
+    /// the `Host` header, which HTTP/1.1 requests must still send (RFC 7230 §5.4).
+    pub fn host(&self) -> Option<&str> {
+        let target = self.parsed_target();
+        match target.form() {
+            target::TargetForm::Absolute => target.authority(),
+            _ => self.header("Host"),
+        }
+    }
+
+    /// Value of the `User-Agent` header, identifying the client software.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.header("User-Agent")
+    }
+
+    /// Serializes the request as it would be sent on the wire. The body is written as-is, framed
+    /// by the request's own headers.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "{} {} {}\r\n", self.method, self.target, self.version)?;
+        for (name, value) in self.headers.iter() {
+            write!(w, "{}: {}\r\n", name, value)?;
+        }
+        w.write_all(b"\r\n")?;
+        if let Some(body) = &self.body {
+            w.write_all(body)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the connection should be kept open after responding to this request.
+    ///
+    /// HTTP/1.0 connections are closed unless the client sent `Connection: keep-alive`, while
+    /// HTTP/1.1 connections are kept alive unless the client sent `Connection: close`.
+    pub fn is_keep_alive(&self) -> bool {
+        match self.version {
+            Version::V1 => self.wants_keep_alive(),
+            _ => !self.wants_close(),
+        }
+    }
+
+    /// The lowercased tokens of every `Connection` header, empty when the client sent none.
+    pub fn connection_tokens(&self) -> Vec<String> {
+        self.header_tokens("Connection")
+    }
+
+    /// The lowercased transfer codings of every `TE` header, without their parameters, such as
+    /// `trailers` when the client accepts trailer fields in a chunked response.
+    pub fn te(&self) -> Vec<String> {
+        self.header_tokens("TE")
+            .into_iter()
+            .filter_map(|coding| {
+                let name = coding.split(';').next().unwrap_or_default().trim_end();
+                (!name.is_empty()).then(|| name.to_owned())
+            })
+            .collect()
+    }
+
+    /// The lowercased field names a chunked body declares it sends as trailers, through the
+    /// `Trailer` header. Once declared, other trailer fields are rejected.
+    pub fn trailer(&self) -> Vec<String> {
+        self.header_tokens("Trailer")
+    }
+
+    fn header_tokens(&self, name: &str) -> Vec<String> {
+        self.headers
+            .get_all(name)
+            .flat_map(|value| value.split(','))
+            .map(|token| token.trim().to_ascii_lowercase())
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+
+    fn has_connection_token(&self, token: &str) -> bool {
+        self.connection_tokens().iter().any(|t| t == token)
+    }
+
+    /// Whether the client sent `Connection: close`.
+    pub fn wants_close(&self) -> bool {
+        self.has_connection_token("close")
+    }
+
+    /// Whether the client sent `Connection: keep-alive`.
+    pub fn wants_keep_alive(&self) -> bool {
+        self.has_connection_token("keep-alive")
+    }
+
+    /// Whether the client sent `Connection: upgrade`.
+    pub fn wants_upgrade(&self) -> bool {
+        self.has_connection_token("upgrade")
+    }
 
-        let mut items = start_line.split(' ');
+    /// Whether the client sent `Expect: 100-continue` and is waiting for an interim response
+    /// before sending the body. Only HTTP/1.1 clients can expect one.
+    pub fn expects_continue(&self) -> bool {
+        self.version == Version::V1_1
+            && self
+                .header("Expect")
+                .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+    }
 
-        let method = items
-            .next()
-            .ok_or(RequestParsingError::Format)?
-            .try_into()?;
+    pub(crate) fn set_peer_addr(&mut self, addr: SocketAddr) {
+        self.peer_addr = Some(addr);
+    }
 
-        let target = items.next().ok_or(RequestParsingError::Format)?.to_owned();
+    /// Whether the framing headers announce a body: chunked transfer coding, or a non-zero
+    /// `Content-Length`. The method doesn't matter, a `GET` can carry a body too. An invalid
+    /// `Content-Length` counts as a body, reading it then fails.
+    pub fn has_body(&self) -> bool {
+        self.is_chunked()
+            || self.is_close_delimited()
+            || self
+                .content_length()
+                .map_or(true, |length| length.is_some_and(|length| length > 0))
+    }
 
-        let version: Version = items
-            .next()
-            .ok_or(RequestParsingError::Format)?
-            .try_into()?;
+    /// Whether the body runs until the client closes its side of the connection. Only HTTP/1.0
+    /// requests can be framed that way, when they announce a body with a `Content-Type` but
+    /// have neither `Content-Length` nor chunked coding, and don't ask to keep the connection
+    /// alive. In HTTP/1.1, a request without framing headers has no body (RFC 7230 §3.3.3).
+    pub(crate) fn is_close_delimited(&self) -> bool {
+        self.version == Version::V1
+            && self.headers.contains("Content-Type")
+            && !self.headers.contains("Content-Length")
+            && !self.is_chunked()
+            && !self.wants_keep_alive()
+    }
 
-        if !version.is_supported() {
-            return Err(RequestParsingError::UnsupportedVersion(version));
+    /// Whether the body uses chunked transfer coding, which takes precedence over any
+    /// `Content-Length`.
+    pub(crate) fn is_chunked(&self) -> bool {
+        self.headers
+            .get_all("Transfer-Encoding")
+            .last()
+            .is_some_and(|value| {
+                value
+                    .rsplit(',')
+                    .next()
+                    .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+            })
+    }
+
+    /// Value of the `Content-Length` header, `None` if absent. Repeated values, in one header or
+    /// several, are accepted as long as they are all the same (RFC 7230 §3.3.2).
+    pub fn content_length(&self) -> Result<Option<u64>, RequestParsingError> {
+        let mut length = None;
+        for value in self
+            .headers
+            .get_all("Content-Length")
+            .flat_map(|v| v.split(','))
+        {
+            let value = value.trim();
+            // Digits only, `u64::from_str` would also take a leading `+`
+            if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(RequestParsingError::InvalidContentLength);
+            }
+            let value: u64 = value
+                .parse()
+                .map_err(|_| RequestParsingError::InvalidContentLength)?;
+            if length.is_some_and(|length| length != value) {
+                return Err(RequestParsingError::InvalidContentLength);
+            }
+            length = Some(value);
         }
+        Ok(length)
+    }
+
+    /// Parses a complete request from an in-memory buffer, as it would be read from a socket.
+    pub fn parse(bytes: &[u8]) -> Result<Self, RequestParsingError> {
+        Self::try_from_reader(&mut RequestReader::from_reader(bytes))
+    }
+
+    pub(crate) fn try_from_reader<R: Read>(
+        reader: &mut RequestReader<R>,
+    ) -> Result<Self, RequestParsingError> {
+        let mut request = Self::read_head(reader)?;
+        request.read_body(reader, DEFAULT_MAX_BODY_SIZE)?;
+        Ok(request)
+    }
 
-        if items.count() != 0 {
-            // Extra arguments to start line
-            return Err(RequestParsingError::Format);
+    /// Reads the start line and headers, leaving the body (if any) unread. The body can then be
+    /// streamed with [`Request::body_reader`].
+    pub fn read_head<R: Read>(reader: &mut RequestReader<R>) -> Result<Self, RequestParsingError> {
+        Self::read_head_noting_version(reader, &mut None)
+    }
+
+    /// Reads the head like [`Request::read_head`], setting `version` as soon as the start line is
+    /// parsed, so an error response can still use the request's version if the headers are
+    /// rejected.
+    pub(crate) fn read_head_noting_version<R: Read>(
+        reader: &mut RequestReader<R>,
+        noted: &mut Option<Version>,
+    ) -> Result<Self, RequestParsingError> {
+        if reader.fill_buf()? == 0 {
+            return Err(RequestParsingError::ConnectionClosed);
         }
+        // Some of the request was received, ending now leaves it truncated
+        let truncated = |e: RequestParsingError| match e.io_error_kind() {
+            Some(io::ErrorKind::UnexpectedEof) => RequestParsingError::IncompleteHead,
+            _ => e,
+        };
+        let start_line = reader
+            .read_start_line()
+            .map_err(RequestParsingError::reading(MessagePart::StartLine))
+            .map_err(truncated)?;
+        let (method, target, version) = parse_start_line(&start_line)?;
+        let target = target.to_owned();
+        *noted = Some(version);
 
-        let headers = reader.read_headers()?;
+        let lines = reader
+            .read_headers()
+            .map_err(RequestParsingError::reading(MessagePart::Headers))
+            .map_err(truncated)?;
+        let headers = Headers::from_lines(&lines)?;
+        validate_host(version, headers.get_all("Host").count())?;
+        validate_transfer_encoding(headers.get_all("Transfer-Encoding"))?;
 
-        // TODO: Body parsing
         Ok(Self {
             method,
             target,
             version,
             headers,
             body: None,
+            trailers: Headers::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
         })
     }
+
+    /// Reads the body following the headers, as delimited by chunked transfer coding or
+    /// `Content-Length`. Errors without reading anything if the declared length exceeds
+    /// `max_size`.
+    ///
+    /// Exactly the body's bytes are consumed: anything the client sent after the body stays
+    /// buffered in the reader, where it is the start of the next pipelined request on a
+    /// keep-alive connection.
+    pub(crate) fn read_body<R: Read>(
+        &mut self,
+        reader: &mut RequestReader<R>,
+        max_size: u64,
+    ) -> Result<(), RequestParsingError> {
+        // Nothing to wait for, the connection may already carry the next request
+        if !self.has_body() {
+            return Ok(());
+        }
+        if self.is_chunked() {
+            let (body, trailers) = chunked::read_chunked(reader, max_size)?;
+            chunked::validate_trailers(&trailers, &self.trailer())?;
+            self.body = Some(body);
+            self.trailers = trailers;
+            return Ok(());
+        }
+        if self.is_close_delimited() {
+            let mut body = Vec::new();
+            reader
+                .take(max_size.saturating_add(1))
+                .read_to_end(&mut body)?;
+            if body.len() as u64 > max_size {
+                return Err(RequestParsingError::BodyTooLarge { limit: max_size });
+            }
+            self.body = Some(body);
+            return Ok(());
+        }
+
+        let Some(length) = self.content_length()? else {
+            return Ok(());
+        };
+        if length > max_size {
+            return Err(RequestParsingError::BodyTooLarge { limit: max_size });
+        }
+
+        // Read incrementally rather than trusting the declared length for the allocation
+        let mut body = Vec::new();
+        reader.take(length).read_to_end(&mut body)?;
+        if (body.len() as u64) < length {
+            return Err(RequestParsingError::IncompleteBody {
+                expected: length,
+                received: body.len() as u64,
+            });
+        }
+
+        self.body = Some(body);
+        Ok(())
+    }
+}
+
+/// HTTP/1.1 requests must carry exactly one `Host` header (RFC 7230 §5.4). It is optional for
+/// HTTP/1.0, but can't be repeated either.
+fn validate_host(version: Version, host_count: usize) -> Result<(), RequestParsingError> {
+    match host_count {
+        0 if version >= Version::V1_1 => Err(RequestParsingError::MissingHost),
+        0 | 1 => Ok(()),
+        _ => Err(RequestParsingError::DuplicateHost),
+    }
+}
+
+/// Transfer codings applied to a body, in order, as listed by `Transfer-Encoding` headers. Only
+/// `chunked` delimits the body, so it must come last (RFC 7230 §3.3.1), and unknown codings are
+/// refused as not implemented.
+fn validate_transfer_encoding<'a>(
+    values: impl Iterator<Item = &'a str>,
+) -> Result<(), RequestParsingError> {
+    let codings: Vec<&str> = values
+        .flat_map(|value| value.split(','))
+        .map(|coding| coding.split(';').next().unwrap_or_default().trim())
+        .filter(|coding| !coding.is_empty())
+        .collect();
+
+    const KNOWN: [&str; 6] = [
+        "chunked",
+        "gzip",
+        "x-gzip",
+        "deflate",
+        "compress",
+        "x-compress",
+    ];
+    if let Some(unknown) = codings
+        .iter()
+        .find(|coding| !KNOWN.iter().any(|known| coding.eq_ignore_ascii_case(known)))
+    {
+        return Err(RequestParsingError::UnsupportedTransferCoding(
+            (*unknown).to_owned(),
+        ));
+    }
+
+    let Some((last, rest)) = codings.split_last() else {
+        return Ok(());
+    };
+    let is_chunked = |coding: &&str| coding.eq_ignore_ascii_case("chunked");
+    if !is_chunked(last) || rest.iter().any(is_chunked) {
+        return Err(RequestParsingError::InvalidTransferEncoding);
+    }
+    Ok(())
+}
+
+/// Checks that the fields of a request line are separated by single spaces, since splitting on
+/// each space would otherwise turn a doubled one into an empty field and shift the others.
+fn validate_separators(line: &str) -> Result<(), RequestParsingError> {
+    // CR and LF are control characters rejected along with the field holding them
+    let reason = if line.bytes().any(|b| matches!(b, b'\t' | b'\x0b' | b'\x0c')) {
+        "fields must be separated by spaces, not tabs or other whitespace"
+    } else if line.starts_with(' ') || line.ends_with(' ') {
+        "leading or trailing space"
+    } else if line.contains("  ") {
+        "fields must be separated by a single space"
+    } else {
+        return Ok(());
+    };
+    Err(RequestParsingError::InvalidStartLine { reason })
+}
+
+/// Errors on any CR or LF in `line`, which was read up to its CRLF ending and so can only hold
+/// bare ones.
+pub(crate) fn reject_bare_line_endings(line: &str) -> Result<(), RequestParsingError> {
+    match line.bytes().find(|&b| b == b'\r' || b == b'\n') {
+        Some(byte) => Err(RequestParsingError::BareLineEnding(byte)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `target` is a non-empty run of URI characters, usable with `method`.
+fn validate_target(method: Method, target: &str) -> Result<(), RequestParsingError> {
+    // The asterisk-form `*` targets the whole server, and is only meaningful for OPTIONS
+    if target.is_empty() || (target == "*" && method != Method::Options) {
+        return Err(RequestParsingError::Format);
+    }
+    if !target.bytes().all(encoding::is_uri_char) {
+        return Err(RequestParsingError::InvalidTarget(target.to_owned()));
+    }
+    Ok(())
+}
+
+/// Splits a request line into its method, target and version.
+fn parse_start_line(line: &str) -> Result<(Method, &str, Version), RequestParsingError> {
+    reject_bare_line_endings(line)?;
+    validate_separators(line)?;
+    let mut items = line.split(' ');
+
+    let method: Method = items
+        .next()
+        .ok_or(RequestParsingError::Format)?
+        .try_into()?;
+
+    let target = items.next().ok_or(RequestParsingError::Format)?;
+    validate_target(method, target)?;
+
+    let version: Version = items
+        .next()
+        .ok_or(RequestParsingError::Format)?
+        .try_into()?;
+
+    if !version.is_supported() {
+        return Err(RequestParsingError::UnsupportedVersion(version));
+    }
+
+    if items.count() != 0 {
+        // Extra arguments to start line
+        return Err(RequestParsingError::Format);
+    }
+
+    Ok((method, target, version))
 }
 
 impl TryFrom<TcpStream> for Request {
     type Error = RequestParsingError;
 
     fn try_from(value: TcpStream) -> Result<Self, Self::Error> {
+        // Capture the address before the stream is moved into the reader. It can be unavailable if
+        // the client already disconnected, which shouldn't fail an otherwise valid request.
+        let peer_addr = value.peer_addr().ok();
         let mut reader = RequestReader::from_reader(value);
-        Self::try_from_reader(&mut reader)
+        let mut request = Self::try_from_reader(&mut reader)?;
+        request.peer_addr = peer_addr;
+        Ok(request)
+    }
+}
+
+impl TryFrom<&[u8]> for Request {
+    type Error = RequestParsingError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse(value)
+    }
+}
+
+impl TryFrom<&str> for Request {
+    type Error = RequestParsingError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::parse(value.as_bytes())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::reader::slow::SlowReader;
 
     #[test]
     fn request_parses_correctly() {
@@ -189,6 +992,7 @@ Accept: */*\r
         assert_eq!(request.target, "/");
         assert_eq!(request.version, Version::V1_1);
         assert_eq!(request.headers.len(), 3);
+        assert_eq!(request.peer_addr(), None);
     }
 
     #[test]
@@ -250,4 +1054,753 @@ Accept: */*\r
 
         assert!(matches!(request_err, RequestParsingError::Format));
     }
+
+    #[test]
+    fn request_errors_when_start_line_separators_invalid() {
+        for (start_line, reason) in [
+            (
+                "GET  / HTTP/1.1",
+                "fields must be separated by a single space",
+            ),
+            (
+                "GET /  HTTP/1.1",
+                "fields must be separated by a single space",
+            ),
+            (
+                "GET\t/\tHTTP/1.1",
+                "fields must be separated by spaces, not tabs or other whitespace",
+            ),
+            (
+                "GET / HTTP/1.1\t",
+                "fields must be separated by spaces, not tabs or other whitespace",
+            ),
+            ("GET / HTTP/1.1 ", "leading or trailing space"),
+            (" GET / HTTP/1.1", "leading or trailing space"),
+        ] {
+            let message = format!("{}\r\nHost: localhost\r\n\r\n", start_line);
+            let request_err = Request::parse(message.as_bytes()).expect_err(start_line);
+
+            assert!(
+                matches!(request_err, RequestParsingError::InvalidStartLine { reason: r } if r == reason),
+                "{:?}: {:?}",
+                start_line,
+                request_err
+            );
+            assert_eq!(request_err.status_code(), StatusCode::BadRequest);
+        }
+    }
+
+    fn parse(message: &str) -> Request {
+        Request::parse(message.as_bytes()).expect("error parsing request")
+    }
+
+    #[test]
+    fn request_parses_http_1_0_version() {
+        let request = parse("GET / HTTP/1.0\r\nAccept: */*\r\n\r\n");
+
+        assert_eq!(request.version, Version::V1);
+    }
+
+    #[test]
+    fn request_parses_without_headers() {
+        let request = parse("GET / HTTP/1.0\r\n\r\n");
+        assert!(request.headers().is_empty());
+
+        let borrowed = borrowed::BorrowedRequest::parse(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        assert!(borrowed.headers().is_empty());
+
+        // HTTP/1.1 requests are read the same way, but rejected for their missing Host
+        let mut reader = RequestReader::from_reader("GET / HTTP/1.1\r\n\r\nNEXT".as_bytes());
+        reader.read_start_line().unwrap();
+        assert!(reader.read_headers().unwrap().is_empty());
+        assert_eq!(reader.peek(), b"NEXT");
+        assert!(matches!(
+            Request::parse(b"GET / HTTP/1.1\r\n\r\n"),
+            Err(RequestParsingError::MissingHost)
+        ));
+    }
+
+    #[test]
+    fn keep_alive_http_1_0_defaults_to_close() {
+        let request = parse("GET / HTTP/1.0\r\nHost: localhost\r\n\r\n");
+
+        assert!(!request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_http_1_0_with_keep_alive() {
+        let request = parse("GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n");
+
+        assert!(request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_http_1_0_with_close() {
+        let request = parse("GET / HTTP/1.0\r\nConnection: close\r\n\r\n");
+
+        assert!(!request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_http_1_1_defaults_to_keep_alive() {
+        let request = parse("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_http_1_1_with_keep_alive() {
+        let request = parse("GET / HTTP/1.1\r\nHost: localhost\r\nConnection: keep-alive\r\n\r\n");
+
+        assert!(request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_http_1_1_with_close() {
+        let request = parse("GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+
+        assert!(!request.is_keep_alive());
+    }
+
+    #[test]
+    fn keep_alive_finds_token_in_list() {
+        let request =
+            parse("GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, close\r\n\r\n");
+
+        assert!(!request.is_keep_alive());
+    }
+
+    #[test]
+    fn request_trims_host_and_user_agent() {
+        let request = parse(
+            "GET / HTTP/1.1\r\nHost:  127.0.0.1:8080 \r\nUser-Agent: Mozilla/5.0 (X11)\r\n\r\n",
+        );
+
+        assert_eq!(request.host(), Some("127.0.0.1:8080"));
+        assert_eq!(request.user_agent(), Some("Mozilla/5.0 (X11)"));
+    }
+
+    #[test]
+    fn has_body_follows_framing_headers() {
+        assert!(!parse("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").has_body());
+        assert!(
+            !parse("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n").has_body()
+        );
+
+        let post = parse("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi");
+        assert!(post.has_body());
+        let chunked = parse(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n",
+        );
+        assert!(chunked.has_body());
+        // Unusual, but the header decides rather than the method
+        let get = parse("GET / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi");
+        assert!(get.has_body());
+        assert_eq!(get.body_bytes(), Some(&b"hi"[..]));
+    }
+
+    #[test]
+    fn http_1_0_body_runs_until_connection_close() {
+        let request = parse("POST / HTTP/1.0\r\nContent-Type: text/plain\r\n\r\nhello\r\nworld");
+        assert!(request.has_body());
+        assert_eq!(request.body_bytes(), Some(&b"hello\r\nworld"[..]));
+
+        // Keep-alive HTTP/1.0 connections can't be delimited by closing them
+        let keep_alive = parse(
+            "POST / HTTP/1.0\r\nContent-Type: text/plain\r\nConnection: keep-alive\r\n\r\nhi",
+        );
+        assert!(!keep_alive.has_body());
+        // Neither is there a body without anything announcing it
+        assert!(!parse("GET / HTTP/1.0\r\n\r\n").has_body());
+    }
+
+    #[test]
+    fn http_1_1_request_without_framing_has_no_body() {
+        let message = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Type: text/plain\r\n\r\nGET /next HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut reader = RequestReader::from_reader(&message[..]);
+
+        let first = Request::try_from_reader(&mut reader).unwrap();
+        assert!(!first.has_body());
+        // What follows is the next request, not a body
+        let next = Request::try_from_reader(&mut reader).unwrap();
+        assert_eq!(next.target(), "/next");
+    }
+
+    #[test]
+    fn http_1_0_body_until_close_respects_max_size() {
+        let message = b"POST / HTTP/1.0\r\nContent-Type: text/plain\r\n\r\ntoo long";
+        let mut reader = RequestReader::from_reader(&message[..]);
+        let mut request = Request::read_head(&mut reader).unwrap();
+
+        assert!(matches!(
+            request.read_body(&mut reader, 4),
+            Err(RequestParsingError::BodyTooLarge { limit: 4 })
+        ));
+    }
+
+    #[test]
+    fn http_1_0_body_until_close_accepts_unlimited_size() {
+        let message = b"POST / HTTP/1.0\r\nContent-Type: text/plain\r\n\r\nbody";
+        let mut reader = RequestReader::from_reader(&message[..]);
+        let mut request = Request::read_head(&mut reader).unwrap();
+
+        request.read_body(&mut reader, u64::MAX).unwrap();
+
+        assert_eq!(request.body_bytes(), Some(&b"body"[..]));
+    }
+
+    #[test]
+    fn request_rebuilds_from_modified_parts() {
+        let request = parse("POST /old HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi");
+
+        let (method, _, version, mut headers, body) = request.into_parts();
+        headers.append("X-Request-Id", "42");
+        let request = Request::from_parts(method, "/new".to_owned(), version, headers, body);
+
+        assert_eq!(request.method(), Method::Post);
+        assert_eq!(request.target(), "/new");
+        assert_eq!(request.version(), Version::V1_1);
+        assert_eq!(request.header("Host"), Some("localhost"));
+        assert_eq!(request.header("X-Request-Id"), Some("42"));
+        assert_eq!(request.body_bytes(), Some(&b"hi"[..]));
+    }
+
+    #[test]
+    fn connection_tokens_empty_without_header() {
+        let request = parse("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(request.connection_tokens().is_empty());
+        assert!(!request.wants_close());
+        assert!(!request.wants_keep_alive());
+        assert!(!request.wants_upgrade());
+    }
+
+    #[test]
+    fn connection_tokens_split_and_lowercase_every_header() {
+        let request = parse(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Keep-Alive ,  Upgrade,\r\nConnection: X-Custom\r\n\r\n",
+        );
+
+        assert_eq!(
+            request.connection_tokens(),
+            ["keep-alive", "upgrade", "x-custom"]
+        );
+        assert!(request.wants_keep_alive());
+        assert!(request.wants_upgrade());
+        assert!(!request.wants_close());
+    }
+
+    #[test]
+    fn request_reads_body_from_content_length() {
+        let request = parse("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello");
+
+        assert_eq!(request.body_str(), Some(Ok("hello")));
+    }
+
+    #[test]
+    fn request_errors_when_body_shorter_than_content_length() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nhello";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request_err = Request::try_from_reader(&mut reader)
+            .expect_err("expected error while parsing request");
+
+        assert!(matches!(
+            request_err,
+            RequestParsingError::IncompleteBody {
+                expected: 10,
+                received: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn request_leaves_bytes_after_body_in_reader() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhelloGET";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::try_from_reader(&mut reader).expect("error parsing request");
+
+        assert_eq!(request.body_str(), Some(Ok("hello")));
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "GET");
+    }
+
+    #[test]
+    fn request_errors_when_body_too_large() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let mut request = Request::read_head(&mut reader).expect("error parsing request head");
+        let request_err = request
+            .read_body(&mut reader, 4)
+            .expect_err("expected error while reading body");
+
+        assert!(matches!(
+            request_err,
+            RequestParsingError::BodyTooLarge { limit: 4 }
+        ));
+    }
+
+    #[test]
+    fn request_expects_continue() {
+        let request = parse("POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\n\r\n");
+        assert!(request.expects_continue());
+
+        let request = parse("POST / HTTP/1.0\r\nExpect: 100-continue\r\n\r\n");
+        assert!(!request.expects_continue());
+    }
+
+    #[test]
+    fn request_exposes_host() {
+        let request = parse("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+
+        assert_eq!(request.host(), Some("example.com"));
+    }
+
+    #[test]
+    fn request_errors_when_http_1_1_missing_host() {
+        let message = "GET / HTTP/1.1\r\nAccept: */*\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request_err = Request::try_from_reader(&mut reader)
+            .expect_err("expected error while parsing request");
+
+        assert!(matches!(request_err, RequestParsingError::MissingHost));
+    }
+
+    #[test]
+    fn request_errors_when_duplicate_host() {
+        for version in ["HTTP/1.0", "HTTP/1.1"] {
+            let message = format!("GET / {}\r\nHost: a\r\nHost: b\r\n\r\n", version);
+            let mut reader = RequestReader::from_reader(message.as_bytes());
+            let request_err = Request::try_from_reader(&mut reader)
+                .expect_err("expected error while parsing request");
+
+            assert!(matches!(request_err, RequestParsingError::DuplicateHost));
+        }
+    }
+
+    #[test]
+    fn request_allows_http_1_0_without_host() {
+        let request = parse("GET / HTTP/1.0\r\nAccept: */*\r\n\r\n");
+
+        assert_eq!(request.host(), None);
+    }
+
+    #[test]
+    fn request_reads_binary_body() {
+        let mut message =
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\n".to_vec();
+        message.extend([0xff, 0x00, 0xfe, 0x01]);
+        let mut reader = RequestReader::from_reader(message.as_slice());
+        let request = Request::try_from_reader(&mut reader).expect("error parsing request");
+
+        assert_eq!(request.body_bytes(), Some(&[0xff, 0x00, 0xfe, 0x01][..]));
+        assert!(matches!(request.body_str(), Some(Err(_))));
+    }
+
+    #[test]
+    fn request_reader_parses_pipelined_requests() {
+        let message = "POST /a HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\none\
+                       GET /b HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+
+        let first = Request::try_from_reader(&mut reader).expect("error parsing first request");
+        let second = Request::try_from_reader(&mut reader).expect("error parsing second request");
+
+        assert_eq!(first.target(), "/a");
+        assert_eq!(first.body_str(), Some(Ok("one")));
+        assert_eq!(second.target(), "/b");
+        assert_eq!(second.body_bytes(), None);
+    }
+
+    #[test]
+    fn method_display_round_trips() {
+        for method in [
+            "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE",
+        ] {
+            assert_eq!(Method::try_from(method).unwrap().to_string(), method);
+        }
+    }
+
+    #[test]
+    fn request_parses_from_bytes_and_str() {
+        let message = "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi";
+
+        let from_bytes = Request::try_from(message.as_bytes()).expect("error parsing request");
+        let from_str = Request::try_from(message).expect("error parsing request");
+
+        assert_eq!(from_bytes.target(), "/upload");
+        assert_eq!(from_str.body_str(), Some(Ok("hi")));
+    }
+
+    #[test]
+    fn request_parse_returns_same_errors() {
+        let request_err = Request::parse(b"FOO / HTTP/1.1\r\nHost: a\r\n\r\n")
+            .expect_err("expected error parsing request");
+
+        assert!(matches!(request_err, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn request_errors_when_too_many_headers() {
+        let mut message = String::from("GET / HTTP/1.1\r\nHost: localhost\r\n");
+        for i in 0..reader::DEFAULT_MAX_HEADERS {
+            message.push_str(&format!("X-Header-{}: value\r\n", i));
+        }
+        message.push_str("\r\n");
+        let request_err = Request::parse(message.as_bytes()).expect_err("expected error");
+
+        assert!(matches!(
+            request_err,
+            RequestParsingError::TooManyHeaders { limit } if limit == reader::DEFAULT_MAX_HEADERS
+        ));
+        assert_eq!(
+            request_err.status_code(),
+            StatusCode::RequestHeaderFieldsTooLarge
+        );
+    }
+
+    #[test]
+    fn request_errors_when_header_line_too_long() {
+        let message = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\nX-Huge: {}\r\nX-After: 1\r\n\r\n",
+            "a".repeat(reader::DEFAULT_MAX_HEADER_LINE)
+        );
+        let request_err = Request::parse(message.as_bytes()).expect_err("expected error");
+
+        assert!(matches!(
+            request_err,
+            RequestParsingError::HeaderLineTooLong { limit } if limit == reader::DEFAULT_MAX_HEADER_LINE
+        ));
+        assert_eq!(
+            request_err.status_code(),
+            StatusCode::RequestHeaderFieldsTooLarge
+        );
+
+        // The same headers are fine within the limit
+        let message = message.replace(&"a".repeat(1024), "");
+        assert!(Request::parse(message.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn request_parses_regardless_of_read_sizes() {
+        let message =
+            "POST /a?b=c HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+                       3\r\nhel\r\n2\r\nlo\r\n0\r\nX-Sum: 1\r\n\r\n";
+        for max in [1, 2, 5, 16] {
+            let mut reader = RequestReader::from_reader(SlowReader::new(message, max));
+            let request = Request::try_from_reader(&mut reader).unwrap();
+
+            assert_eq!(request.path(), "/a", "{}", max);
+            assert_eq!(request.header("Host"), Some("localhost"));
+            assert_eq!(request.body_str(), Some(Ok("hello")));
+            assert_eq!(request.trailers().get("X-Sum"), Some("1"));
+        }
+
+        // A socket with nothing to read yet, past its read timeout
+        let mut reader = RequestReader::from_reader(SlowReader::new(message, 8).with_would_block());
+        assert!(Request::try_from_reader(&mut reader)
+            .unwrap_err()
+            .is_timeout());
+    }
+
+    #[test]
+    fn request_reads_chunked_body_with_trailers() {
+        let request = parse(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTrailer: Content-MD5\r\n\r\n\
+             5\r\nhello\r\n0\r\nContent-MD5: 5d41402abc4b2a76b9719d911017c592\r\n\r\n",
+        );
+
+        assert_eq!(request.body_str(), Some(Ok("hello")));
+        assert_eq!(
+            request.trailers().get("Content-MD5"),
+            Some("5d41402abc4b2a76b9719d911017c592")
+        );
+    }
+
+    #[test]
+    fn request_parses_te_and_trailer_headers() {
+        let request = parse(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nTE: Trailers, deflate;q=0.5\r\n\
+             Trailer: Content-MD5, X-Sum\r\nTrailer: Expires\r\n\r\n",
+        );
+
+        assert_eq!(request.te(), ["trailers", "deflate"]);
+        assert_eq!(request.trailer(), ["content-md5", "x-sum", "expires"]);
+        assert!(parse("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .te()
+            .is_empty());
+    }
+
+    #[test]
+    fn request_rejects_undeclared_trailers() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTrailer: X-Sum\r\n\r\n\
+                       2\r\nhi\r\n0\r\nx-sum: 1\r\nX-Other: 2\r\n\r\n";
+
+        let err = Request::parse(message.as_bytes()).unwrap_err();
+        assert!(matches!(&err, RequestParsingError::UndeclaredTrailer(name) if name == "X-Other"));
+        assert_eq!(err.status_code(), StatusCode::BadRequest);
+
+        // Without a Trailer header, any trailer is accepted
+        let message = message.replace("Trailer: X-Sum\r\n", "");
+        assert!(Request::parse(message.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn request_writes_back_to_wire_format() {
+        let message = "POST /a?b=c HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi";
+        let mut out = Vec::new();
+        parse(message).write_to(&mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), message);
+    }
+
+    #[test]
+    fn request_reports_clean_close_before_request() {
+        let request_err = Request::parse(b"").expect_err("expected error parsing request");
+
+        assert!(matches!(request_err, RequestParsingError::ConnectionClosed));
+    }
+
+    #[test]
+    fn request_reports_eof_mid_head_as_incomplete() {
+        for truncated in [
+            &b"GET / HTTP/1.1\r\nHo"[..],
+            b"GET / HTTP/1.1\r\nHost: example.com",
+            b"GET / HTTP/1.1\r\nHost: example.com\r\n",
+            b"GET / HT",
+        ] {
+            let request_err =
+                Request::parse(truncated).expect_err("expected error parsing request");
+
+            assert!(matches!(request_err, RequestParsingError::IncompleteHead));
+            assert_eq!(request_err.status_code(), StatusCode::BadRequest);
+            assert!(request_err.is_unexpected_eof());
+            assert!(!request_err.is_timeout());
+        }
+    }
+
+    #[test]
+    fn request_error_reports_io_error_kind() {
+        let timeout = RequestParsingError::from(io::Error::from(io::ErrorKind::WouldBlock));
+        assert!(timeout.is_timeout());
+        assert_eq!(timeout.io_error_kind(), Some(io::ErrorKind::TimedOut));
+
+        let reset = RequestParsingError::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(reset.io_error_kind(), Some(io::ErrorKind::ConnectionReset));
+        assert!(!reset.is_unexpected_eof());
+
+        let incomplete = RequestParsingError::IncompleteBody {
+            expected: 10,
+            received: 5,
+        };
+        assert!(incomplete.is_unexpected_eof());
+        assert_eq!(incomplete.io_error_kind(), None);
+    }
+
+    #[test]
+    fn request_carries_extensions() {
+        let mut request = parse("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        request.extensions_mut().insert("user");
+
+        assert_eq!(request.extensions().get::<&str>(), Some(&"user"));
+    }
+
+    #[test]
+    fn method_safe_and_idempotent_classification() {
+        let cases = [
+            (Method::Get, true, true),
+            (Method::Head, true, true),
+            (Method::Post, false, false),
+            (Method::Put, false, true),
+            (Method::Delete, false, true),
+            (Method::Connect, false, false),
+            (Method::Options, true, true),
+            (Method::Trace, true, true),
+        ];
+
+        for (method, safe, idempotent) in cases {
+            assert_eq!(method.is_safe(), safe, "{}", method);
+            assert_eq!(method.is_idempotent(), idempotent, "{}", method);
+        }
+    }
+
+    #[test]
+    fn request_accepts_asterisk_form_only_for_options() {
+        let request = parse("OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert_eq!(request.target(), "*");
+
+        let request_err = Request::parse(b"GET * HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect_err("expected error parsing request");
+        assert!(matches!(request_err, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn request_rejects_control_characters_in_target() {
+        let request_err = Request::parse(b"GET /file\0.txt HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect_err("expected error parsing request");
+        assert!(
+            matches!(request_err, RequestParsingError::InvalidTarget(target) if target == "/file\0.txt")
+        );
+
+        // A bare LF doesn't end the start line, and must not smuggle a header into the target
+        let request_err =
+            Request::parse(b"GET /a\nX-Injected: 1 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .expect_err("expected error parsing request");
+        assert!(matches!(
+            request_err,
+            RequestParsingError::BareLineEnding(b'\n')
+        ));
+    }
+
+    #[test]
+    fn request_rejects_bare_cr_and_lf_in_headers() {
+        for (message, byte) in [
+            (
+                &b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\nInjected: 1\r\n\r\n"[..],
+                b'\n',
+            ),
+            (
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\rb\r\n\r\n",
+                b'\r',
+            ),
+            (
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Test: a\r\r\n\r\n",
+                b'\r',
+            ),
+            (b"GET / HTTP/1.1\rHost: localhost\r\n\r\n", b'\r'),
+        ] {
+            let request_err = Request::parse(message).expect_err("expected error parsing request");
+
+            assert!(
+                matches!(request_err, RequestParsingError::BareLineEnding(b) if b == byte),
+                "{:?}",
+                request_err
+            );
+            assert_eq!(request_err.status_code(), StatusCode::BadRequest);
+        }
+    }
+
+    #[test]
+    fn request_validates_transfer_codings() {
+        let message = |coding: &str| {
+            format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: {}\r\n\r\n\
+                 2\r\nhi\r\n0\r\n\r\n",
+                coding
+            )
+        };
+
+        assert_eq!(
+            parse(&message("chunked")).body_bytes(),
+            Some("hi".as_bytes())
+        );
+        assert_eq!(
+            parse(&message("gzip, chunked")).body_bytes(),
+            Some("hi".as_bytes())
+        );
+
+        let request_err =
+            Request::parse(message("frobnicate").as_bytes()).expect_err("expected error");
+        assert!(
+            matches!(&request_err, RequestParsingError::UnsupportedTransferCoding(c) if c == "frobnicate")
+        );
+        assert_eq!(request_err.status_code(), StatusCode::NotImplemented);
+
+        for coding in ["gzip", "chunked, gzip", "chunked, chunked"] {
+            let request_err =
+                Request::parse(message(coding).as_bytes()).expect_err("expected error");
+            assert!(matches!(
+                request_err,
+                RequestParsingError::InvalidTransferEncoding
+            ));
+        }
+    }
+
+    #[test]
+    fn request_validates_content_length() {
+        let content_length = |headers: &str| {
+            let message = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n", headers);
+            let mut reader = RequestReader::from_reader(message.as_bytes());
+            Request::read_head(&mut reader).unwrap().content_length()
+        };
+
+        assert_eq!(content_length("").unwrap(), None);
+        assert_eq!(
+            content_length("Content-Length:  42 \r\n").unwrap(),
+            Some(42)
+        );
+        assert_eq!(
+            content_length("Content-Length: 42\r\nContent-Length: 42, 42\r\n").unwrap(),
+            Some(42)
+        );
+        for invalid in ["abc", "-1", "+1", ""] {
+            let headers = format!("Content-Length: {}\r\n", invalid);
+            assert!(matches!(
+                content_length(&headers),
+                Err(RequestParsingError::InvalidContentLength)
+            ));
+        }
+        assert!(matches!(
+            content_length("Content-Length: 42\r\nContent-Length: 43\r\n"),
+            Err(RequestParsingError::InvalidContentLength)
+        ));
+    }
+
+    #[test]
+    fn request_locates_invalid_utf8() {
+        let request_err = Request::parse(b"GET /caf\xe9 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .expect_err("expected error parsing request");
+        assert!(matches!(
+            request_err,
+            RequestParsingError::InvalidEncoding {
+                part: Some(MessagePart::StartLine),
+                valid_up_to: 8,
+            }
+        ));
+
+        let request_err = Request::parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nX: \xff\r\n\r\n")
+            .expect_err("expected error parsing request");
+        assert!(matches!(
+            request_err,
+            RequestParsingError::InvalidEncoding {
+                part: Some(MessagePart::Headers),
+                valid_up_to: 3,
+            }
+        ));
+        assert_eq!(
+            request_err.to_string(),
+            "invalid UTF-8 in the headers after byte 3"
+        );
+    }
+
+    #[test]
+    fn parse_rejects_adversarial_input_without_panicking() {
+        // Inputs like those the `parse_request` fuzz target generates: truncated at every step,
+        // binary, or with delimiters in unexpected places. Also the seeds of its corpus
+        let messages: [&[u8]; 17] = [
+            b"",
+            b"\r\n\r\n\r\n",
+            b"GET",
+            b"GET / HTTP/1.1\r",
+            b"GET / HTTP/1.1\r\nHost",
+            b"GET / HTTP/1.1\r\n:\r\n\r\n",
+            b"\xef\xbb\xbf\xef\xbb\xbf\r\n",
+            b"\x00\xff\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\r\nContent-Length: 99999999999999999999999\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\r\nContent-Length: -1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffffffff\r\n",
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nX",
+            b"GET http:// HTTP/1.1\r\nHost: a\r\nRange: bytes=-0,0-\r\n\r\n",
+            b"GET / HTTP/1.1\nHost: a\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\rX-Smuggled: 1\r\n\r\n",
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n5\nhello\r\n0\r\n\r\n",
+        ];
+
+        for message in messages {
+            for end in 0..=message.len() {
+                let _ = Request::parse(&message[..end]);
+            }
+        }
+    }
 }
@@ -0,0 +1,81 @@
+//! HTTP Basic authentication (RFC 7617).
+
+use super::{base64, Request, Response, StatusCode};
+
+impl Request {
+    /// Username and password from an `Authorization: Basic` header. Returns `None` if the header
+    /// is missing, uses another scheme, or doesn't hold valid base64 `user:password` credentials.
+    pub fn basic_auth(&self) -> Option<(String, String)> {
+        let (scheme, credentials) = self.header("Authorization")?.split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("Basic") {
+            return None;
+        }
+
+        let decoded = String::from_utf8(base64::decode(credentials.trim())?).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+        Some((user.to_owned(), password.to_owned()))
+    }
+}
+
+impl Response {
+    /// A `401 Unauthorized` response asking the client for Basic credentials for `realm`.
+    pub fn basic_auth_challenge(realm: &str) -> Self {
+        let realm = realm.replace('\\', "\\\\").replace('"', "\\\"");
+        Self::new(StatusCode::Unauthorized)
+            .with_header("WWW-Authenticate", format!("Basic realm=\"{}\"", realm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::RequestReader;
+
+    fn request(authorization: &str) -> Request {
+        let message = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nAuthorization: {}\r\n\r\n",
+            authorization
+        );
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        Request::try_from_reader(&mut reader).expect("error parsing request")
+    }
+
+    #[test]
+    fn basic_auth_decodes_credentials() {
+        // "aladdin:open:sesame", the password keeps everything after the first colon
+        let request = request("Basic YWxhZGRpbjpvcGVuOnNlc2FtZQ==");
+
+        assert_eq!(
+            request.basic_auth(),
+            Some(("aladdin".to_owned(), "open:sesame".to_owned()))
+        );
+    }
+
+    #[test]
+    fn basic_auth_is_none_for_other_schemes_or_bad_base64() {
+        assert_eq!(request("Bearer YWxhZGRpbjpvcGVu").basic_auth(), None);
+        assert_eq!(request("Basic not*base64").basic_auth(), None);
+        // "nocolon"
+        assert_eq!(request("Basic bm9jb2xvbg==").basic_auth(), None);
+    }
+
+    #[test]
+    fn basic_auth_is_none_without_header() {
+        let message = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::try_from_reader(&mut reader).expect("error parsing request");
+
+        assert_eq!(request.basic_auth(), None);
+    }
+
+    #[test]
+    fn basic_auth_challenge_sets_realm() {
+        let response = Response::basic_auth_challenge("admin \"zone\"");
+
+        assert_eq!(response.status(), StatusCode::Unauthorized);
+        assert_eq!(
+            response.header("WWW-Authenticate"),
+            Some("Basic realm=\"admin \\\"zone\\\"\"")
+        );
+    }
+}
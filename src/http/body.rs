@@ -0,0 +1,387 @@
+//! Streaming request bodies, read as they arrive instead of buffered in memory.
+
+use std::io::{self, Read};
+
+use super::{chunked, reader::RequestReader, Headers, Request, RequestParsingError};
+
+/// How a request body was delimited on the wire, for forwarding it with the same framing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFraming {
+    /// Neither `Transfer-Encoding: chunked` nor `Content-Length`, so no body.
+    None,
+    /// `Content-Length` delimited, with the declared length, which may be 0.
+    ContentLength(u64),
+    /// Chunked transfer coding, whatever the `Content-Length`.
+    Chunked,
+    /// Until the client closes the connection, for some HTTP/1.0 requests, see
+    /// [`Request::has_body`].
+    UntilClose,
+}
+
+#[derive(Debug)]
+enum Framing {
+    /// `Content-Length` delimited, with this many bytes left.
+    Length(u64),
+    /// Chunked, with this many bytes left in the current chunk. At 0, the next chunk size line
+    /// is read.
+    Chunked(u64),
+    /// Until the underlying reader ends.
+    UntilEof,
+    Done,
+}
+
+/// [`Read`]s exactly the body of a request from the connection, then reports EOF. Bytes after
+/// the body, such as a pipelined request, are left in the [`RequestReader`].
+pub struct BodyReader<'a, R: Read> {
+    reader: &'a mut RequestReader<R>,
+    framing: Framing,
+    trailers: Headers,
+    declared_trailers: Vec<String>,
+}
+
+impl<'a, R: Read> BodyReader<'a, R> {
+    /// Trailer fields sent after a chunked body, available once the body was read to its end.
+    pub fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    fn read_chunk_size(&mut self) -> io::Result<()> {
+        let size = chunked::read_chunk_size(self.reader).map_err(|e| match e {
+            RequestParsingError::Io(e) => e,
+            RequestParsingError::Timeout => io::ErrorKind::TimedOut.into(),
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        })?;
+
+        if size == 0 {
+            let lines = self.reader.read_headers().map_err(into_io)?;
+            self.trailers = Headers::from_lines(&lines)
+                .and_then(|trailers| {
+                    chunked::validate_trailers(&trailers, &self.declared_trailers)?;
+                    Ok(trailers)
+                })
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            self.framing = Framing::Done;
+        } else {
+            self.framing = Framing::Chunked(size);
+        }
+        Ok(())
+    }
+}
+
+fn into_io(e: super::reader::RequestReaderError) -> io::Error {
+    match e {
+        super::reader::RequestReaderError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+impl<R: Read> Read for BodyReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            match self.framing {
+                Framing::Done => return Ok(0),
+                Framing::UntilEof => {
+                    let n = self.reader.read(buf)?;
+                    if n == 0 {
+                        self.framing = Framing::Done;
+                    }
+                    return Ok(n);
+                }
+                Framing::Length(remaining) | Framing::Chunked(remaining) if remaining > 0 => {
+                    let max = remaining.min(buf.len() as u64) as usize;
+                    let n = self.reader.read(&mut buf[..max])?;
+                    if n == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    let remaining = remaining - n as u64;
+                    self.framing = match self.framing {
+                        Framing::Length(_) if remaining == 0 => Framing::Done,
+                        Framing::Length(_) => Framing::Length(remaining),
+                        _ => Framing::Chunked(remaining),
+                    };
+                    if let Framing::Chunked(0) = self.framing {
+                        // Chunk data must be followed by CRLF, anything else is rejected
+                        // without buffering it
+                        self.reader
+                            .read_line_limited(0)
+                            .map_err(into_io)?
+                            .ok_or_else(|| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    "chunk not followed by CRLF",
+                                )
+                            })?;
+                    }
+                    return Ok(n);
+                }
+                Framing::Length(_) => {
+                    self.framing = Framing::Done;
+                }
+                Framing::Chunked(_) => self.read_chunk_size()?,
+            }
+        }
+    }
+}
+
+impl Request {
+    /// Framing of the body, as announced by the request head. A malformed `Content-Length`,
+    /// which parsed requests can't have, counts as [`BodyFraming::None`].
+    pub fn body_framing(&self) -> BodyFraming {
+        if self.is_chunked() {
+            return BodyFraming::Chunked;
+        }
+        if self.is_close_delimited() {
+            return BodyFraming::UntilClose;
+        }
+        match self.content_length() {
+            Ok(Some(length)) => BodyFraming::ContentLength(length),
+            Ok(None) | Err(_) => BodyFraming::None,
+        }
+    }
+
+    /// Streams the body of a request whose head was read from `reader` with
+    /// [`Request::read_head`], instead of buffering it. Requests without a body give a reader
+    /// that is immediately at EOF.
+    pub fn body_reader<'a, R: Read>(
+        &self,
+        reader: &'a mut RequestReader<R>,
+    ) -> Result<BodyReader<'a, R>, RequestParsingError> {
+        let framing = if self.is_chunked() {
+            Framing::Chunked(0)
+        } else if self.is_close_delimited() {
+            Framing::UntilEof
+        } else {
+            match self.content_length()? {
+                Some(length) if length > 0 => Framing::Length(length),
+                _ => Framing::Done,
+            }
+        };
+
+        Ok(BodyReader {
+            reader,
+            framing,
+            trailers: Headers::new(),
+            declared_trailers: self.trailer(),
+        })
+    }
+
+    /// Reads and discards the unread body of a request whose head was read from `reader`,
+    /// returning its length. Once drained, the next pipelined request can be read from the same
+    /// connection, such as after answering with an error without looking at the body.
+    ///
+    /// Bodies larger than `max_size` fail with [`RequestParsingError::BodyTooLarge`], without
+    /// reading a declared length past it. The connection should then be closed instead.
+    pub fn drain_body<R: Read>(
+        &self,
+        reader: &mut RequestReader<R>,
+        max_size: u64,
+    ) -> Result<u64, RequestParsingError> {
+        if self
+            .content_length()?
+            .is_some_and(|length| length > max_size)
+        {
+            return Err(RequestParsingError::BodyTooLarge { limit: max_size });
+        }
+
+        let body = self.body_reader(reader)?;
+        let drained = io::copy(&mut body.take(max_size.saturating_add(1)), &mut io::sink())?;
+        if drained > max_size {
+            return Err(RequestParsingError::BodyTooLarge { limit: max_size });
+        }
+        Ok(drained)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::slow::SlowReader;
+
+    #[test]
+    fn body_framing_reflects_head() {
+        let framing = |headers: &str| {
+            let message = format!("POST / HTTP/1.1\r\nHost: a\r\n{}\r\n", headers);
+            let mut reader = RequestReader::from_reader(message.as_bytes());
+            Request::read_head(&mut reader)
+                .expect("error reading head")
+                .body_framing()
+        };
+
+        assert_eq!(framing(""), BodyFraming::None);
+        assert_eq!(
+            framing("Content-Length: 12\r\n"),
+            BodyFraming::ContentLength(12)
+        );
+        assert_eq!(
+            framing("Content-Length: 0\r\n"),
+            BodyFraming::ContentLength(0)
+        );
+        assert_eq!(
+            framing("Transfer-Encoding: gzip, chunked\r\n"),
+            BodyFraming::Chunked
+        );
+
+        let mut reader = RequestReader::from_reader(
+            &b"POST / HTTP/1.0\r\nContent-Type: text/plain\r\n\r\nbody"[..],
+        );
+        let request = Request::read_head(&mut reader).unwrap();
+        assert_eq!(request.body_framing(), BodyFraming::UntilClose);
+        let mut body = Vec::new();
+        request
+            .body_reader(&mut reader)
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, b"body");
+    }
+
+    /// Streams the body of `message`, also read in pieces of a few bytes to check the result
+    /// doesn't depend on where reads split it.
+    fn stream_body(message: &str) -> (Vec<u8>, Headers, String) {
+        let whole = stream_body_from(RequestReader::from_reader(message.as_bytes()));
+        for max in [1, 3] {
+            let pieces =
+                stream_body_from(RequestReader::from_reader(SlowReader::new(message, max)));
+            assert_eq!(pieces, whole, "read {} bytes at a time", max);
+        }
+        whole
+    }
+
+    fn stream_body_from<R: Read>(mut reader: RequestReader<R>) -> (Vec<u8>, Headers, String) {
+        let request = Request::read_head(&mut reader).expect("error reading head");
+        let mut body_reader = request.body_reader(&mut reader).unwrap();
+
+        let mut body = Vec::new();
+        body_reader
+            .read_to_end(&mut body)
+            .expect("error reading body");
+        let trailers = body_reader.trailers().clone();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        (body, trailers, rest)
+    }
+
+    #[test]
+    fn body_reader_reads_content_length_body() {
+        let (body, _, rest) =
+            stream_body("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhelloGET");
+
+        assert_eq!(body, b"hello");
+        assert_eq!(rest, "GET");
+    }
+
+    #[test]
+    fn body_reader_decodes_chunked_body() {
+        let (body, trailers, rest) = stream_body(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+             5\r\nhello\r\n6\r\n world\r\n0\r\nX-Checksum: 1\r\n\r\nGET",
+        );
+
+        assert_eq!(body, b"hello world");
+        assert_eq!(trailers.get("X-Checksum"), Some("1"));
+        assert_eq!(rest, "GET");
+    }
+
+    #[test]
+    fn body_reader_rejects_chunk_without_crlf_without_buffering() {
+        let head =
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi";
+        // The chunk data is followed by a stream of bytes that never ends the line
+        let endless = head
+            .as_bytes()
+            .chain(io::repeat(b'a').take(64 * 1024 * 1024));
+        let mut reader = RequestReader::from_reader(endless);
+        let request = Request::read_head(&mut reader).unwrap();
+
+        let err = request
+            .body_reader(&mut reader)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(reader.bytes_read() < 64 * 1024, "{}", reader.bytes_read());
+    }
+
+    #[test]
+    fn body_reader_checks_trailers_against_declaration() {
+        let (_, trailers, _) = stream_body(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n\
+             2\r\nhi\r\n0\r\nX-Checksum: 1\r\n\r\n",
+        );
+        assert_eq!(trailers.get("X-Checksum"), Some("1"));
+
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\n\r\n\
+                       2\r\nhi\r\n0\r\nX-Other: 1\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::read_head(&mut reader).unwrap();
+        let err = request
+            .body_reader(&mut reader)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn body_reader_is_empty_without_body() {
+        let (body, _, _) = stream_body("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn body_reader_errors_when_body_cut_short() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nhello";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::read_head(&mut reader).unwrap();
+
+        let e = request
+            .body_reader(&mut reader)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .expect_err("expected error");
+
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn drain_body_leaves_next_request_readable() {
+        let message = "POST /a HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+                       5\r\nhello\r\n0\r\n\r\n\
+                       POST /b HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+
+        let first = Request::read_head(&mut reader).unwrap();
+        assert_eq!(first.drain_body(&mut reader, 1024).unwrap(), 5);
+
+        let mut second = Request::read_head(&mut reader).unwrap();
+        assert_eq!(second.target(), "/b");
+        second.read_body(&mut reader, 1024).unwrap();
+        assert_eq!(second.body_bytes(), Some(&b"abc"[..]));
+    }
+
+    #[test]
+    fn drain_body_errors_past_limit() {
+        let message = "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+                       5\r\nhello\r\n0\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::read_head(&mut reader).unwrap();
+
+        let e = request
+            .drain_body(&mut reader, 4)
+            .expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::BodyTooLarge { limit: 4 }));
+
+        // No limit at all doesn't overflow
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::read_head(&mut reader).unwrap();
+        assert_eq!(request.drain_body(&mut reader, u64::MAX).unwrap(), 5);
+    }
+}
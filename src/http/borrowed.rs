@@ -0,0 +1,185 @@
+//! Request head parsed without per-header allocations, borrowing from a buffer returned by
+//! [`RequestReader::read_head_bytes`](super::reader::RequestReader::read_head_bytes).
+
+use super::{
+    headers, parse_start_line, validate_host, validate_transfer_encoding, Extensions, Headers,
+    Method, Request, RequestParsingError, Version,
+};
+
+#[derive(Debug)]
+pub struct BorrowedRequest<'a> {
+    method: Method,
+    target: &'a str,
+    version: Version,
+    headers: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> BorrowedRequest<'a> {
+    /// Parses a request head, terminated by an empty line. Anything after it is ignored.
+    pub fn parse(head: &'a [u8]) -> Result<Self, RequestParsingError> {
+        let head = std::str::from_utf8(head).map_err(|_| RequestParsingError::Format)?;
+        let head = match head.find("\r\n\r\n") {
+            Some(idx) => &head[..idx],
+            None => return Err(RequestParsingError::Format),
+        };
+
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next().ok_or(RequestParsingError::Format)?;
+        let (method, target, version) = parse_start_line(start_line)?;
+
+        let headers = lines
+            .map(headers::parse_line)
+            .collect::<Result<Vec<_>, RequestParsingError>>()?;
+
+        let request = Self {
+            method,
+            target,
+            version,
+            headers,
+        };
+        let host_count = request.headers_named("Host").count();
+        validate_host(version, host_count)?;
+        validate_transfer_encoding(request.headers_named("Transfer-Encoding"))?;
+        Ok(request)
+    }
+
+    fn headers_named<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'a str> + 'b {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Headers as `(name, value)` pairs, in the order they were received.
+    pub fn headers(&self) -> &[(&'a str, &'a str)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers_named(name).next()
+    }
+
+    /// Copies the head into an owned [`Request`], without a body.
+    pub fn to_owned_request(&self) -> Request {
+        Request {
+            method: self.method,
+            target: self.target.to_owned(),
+            version: self.version,
+            headers: {
+                let mut headers = Headers::new();
+                for (name, value) in &self.headers {
+                    headers.append(*name, *value);
+                }
+                headers
+            },
+            body: None,
+            trailers: Headers::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::http::reader::RequestReader;
+
+    const MESSAGE: &str = "GET /index.html HTTP/1.1\r
+Host: 127.0.0.1:8080\r
+User-Agent: Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0\r
+Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r
+Accept-Language: en-CA,en-US;q=0.7,en;q=0.3\r
+Accept-Encoding: gzip, deflate, br, zstd\r
+Connection: keep-alive\r
+Cookie: session=0123456789abcdef; theme=dark\r
+Upgrade-Insecure-Requests: 1\r
+Sec-Fetch-Dest: document\r
+Sec-Fetch-Mode: navigate\r
+Sec-Fetch-Site: none\r
+Priority: u=0, i\r
+\r
+";
+
+    #[test]
+    fn borrowed_request_parses_correctly() {
+        let mut reader = RequestReader::from_reader(MESSAGE.as_bytes());
+        let head = reader.read_head_bytes().expect("error reading head");
+        let request = BorrowedRequest::parse(&head).expect("error parsing request");
+
+        assert_eq!(request.method(), Method::Get);
+        assert_eq!(request.target(), "/index.html");
+        assert_eq!(request.version(), Version::V1_1);
+        assert_eq!(request.headers().len(), 12);
+        assert_eq!(request.header("host"), Some("127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn borrowed_request_matches_owned_request() {
+        let borrowed = BorrowedRequest::parse(MESSAGE.as_bytes()).expect("error parsing request");
+        let mut reader = RequestReader::from_reader(MESSAGE.as_bytes());
+        let owned = Request::try_from_reader(&mut reader).expect("error parsing request");
+
+        let converted = borrowed.to_owned_request();
+        assert_eq!(converted.target(), owned.target());
+        assert_eq!(converted.headers(), owned.headers());
+    }
+
+    #[test]
+    fn borrowed_request_errors_when_incomplete() {
+        let e = BorrowedRequest::parse(b"GET / HTTP/1.1\r\nHost: localhost\r\n")
+            .expect_err("expected error for incomplete head");
+
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+
+    /// Compares the owned and borrowed parsing paths, run with
+    /// `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_owned_vs_borrowed_headers() {
+        const ITERATIONS: u32 = 100_000;
+
+        let time = |f: &dyn Fn()| {
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                f();
+            }
+            start.elapsed() / ITERATIONS
+        };
+
+        let owned = time(&|| {
+            let mut reader = RequestReader::from_reader(MESSAGE.as_bytes());
+            std::hint::black_box(Request::read_head(&mut reader).unwrap());
+        });
+        let borrowed = time(&|| {
+            let mut reader = RequestReader::from_reader(MESSAGE.as_bytes());
+            let head = reader.read_head_bytes().unwrap();
+            std::hint::black_box(BorrowedRequest::parse(&head).unwrap());
+        });
+        // Parsing alone, without going through the reader
+        let borrowed_parse = time(&|| {
+            std::hint::black_box(BorrowedRequest::parse(MESSAGE.as_bytes()).unwrap());
+        });
+
+        println!(
+            "owned: {:?}/iter, borrowed: {:?}/iter, borrowed parse only: {:?}/iter",
+            owned, borrowed, borrowed_parse
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Decoding of `Transfer-Encoding: chunked` request bodies (RFC 7230 §4.1).
+
+use std::io::Read;
+
+use super::{reader::RequestReader, Headers, MessagePart, RequestParsingError};
+
+/// Largest chunk accepted, whatever the body size limit.
+pub(crate) const MAX_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Longest chunk size line accepted, extensions included.
+const MAX_SIZE_LINE: usize = 1024;
+
+/// Reads a chunk size line, ignoring any chunk extensions. Errors on sizes over
+/// [`MAX_CHUNK_SIZE`], before any of the chunk is read.
+pub(crate) fn read_chunk_size<R: Read>(
+    reader: &mut RequestReader<R>,
+) -> Result<u64, RequestParsingError> {
+    let size_line = reader
+        .read_line_limited(MAX_SIZE_LINE)
+        .map_err(RequestParsingError::reading(MessagePart::Body))?
+        .ok_or(RequestParsingError::Format)?;
+    // Chunk extensions (`;name=value`) carry no meaning for us and are ignored
+    let size = size_line.split(';').next().unwrap_or_default().trim();
+    let size = u64::from_str_radix(size, 16).map_err(|_| RequestParsingError::Format)?;
+
+    if size > MAX_CHUNK_SIZE {
+        return Err(RequestParsingError::ChunkTooLarge {
+            limit: MAX_CHUNK_SIZE,
+        });
+    }
+    Ok(size)
+}
+
+/// Reads a chunked body up to and including its trailer section, returning the decoded body and
+/// any trailer fields. Errors as soon as a chunk declares a size that would take the decoded body
+/// over `max_size`.
+pub(crate) fn read_chunked<R: Read>(
+    reader: &mut RequestReader<R>,
+    max_size: u64,
+) -> Result<(Vec<u8>, Headers), RequestParsingError> {
+    let mut body = Vec::new();
+    loop {
+        let size = read_chunk_size(reader)?;
+        if size == 0 {
+            break;
+        }
+        if body.len() as u64 + size > max_size {
+            return Err(RequestParsingError::BodyTooLarge { limit: max_size });
+        }
+
+        let start = body.len();
+        reader.take(size).read_to_end(&mut body)?;
+        let received = (body.len() - start) as u64;
+        if received < size {
+            return Err(RequestParsingError::IncompleteBody {
+                expected: size,
+                received,
+            });
+        }
+        // Chunk data must be followed by CRLF, anything else is rejected without buffering it
+        reader
+            .read_line_limited(0)
+            .map_err(RequestParsingError::reading(MessagePart::Body))?
+            .ok_or(RequestParsingError::Format)?;
+    }
+
+    // The last chunk is followed by optional trailer fields, then an empty line
+    let lines = reader
+        .read_headers()
+        .map_err(RequestParsingError::reading(MessagePart::Body))?;
+    let trailers = Headers::from_lines(&lines)?;
+    Ok((body, trailers))
+}
+
+/// Checks that every trailer field was listed in the `Trailer` header, as `declared`. Bodies
+/// without a `Trailer` header may send any, the header being recommended rather than required
+/// (RFC 7230 §4.4).
+pub(crate) fn validate_trailers(
+    trailers: &Headers,
+    declared: &[String],
+) -> Result<(), RequestParsingError> {
+    if declared.is_empty() {
+        return Ok(());
+    }
+    match trailers
+        .names()
+        .find(|name| !declared.iter().any(|d| d.eq_ignore_ascii_case(name)))
+    {
+        Some(name) => Err(RequestParsingError::UndeclaredTrailer(name.to_owned())),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::slow::SlowReader;
+
+    fn decode(message: &str) -> Result<(Vec<u8>, Headers), RequestParsingError> {
+        read_chunked(&mut RequestReader::from_reader(message.as_bytes()), 1024)
+    }
+
+    #[test]
+    fn chunked_decodes_chunks() {
+        let (body, trailers) = decode("5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n").unwrap();
+
+        assert_eq!(body, b"hello, world");
+        assert!(trailers.is_empty());
+    }
+
+    #[test]
+    fn chunked_decodes_body_read_one_byte_at_a_time() {
+        let message =
+            b"5;ext=1\r\nhello\r\n1\r\n,\r\n10\r\n world, chunked!\r\n0\r\nX-Sum: 1\r\n\r\nNEXT";
+        // A single byte per read, so every size line, chunk and CRLF straddles reads
+        let mut reader = RequestReader::from_reader(SlowReader::new(&message[..], 1));
+
+        let (body, trailers) = read_chunked(&mut reader, 1024).unwrap();
+
+        assert_eq!(body, b"hello, world, chunked!");
+        assert_eq!(trailers.get("X-Sum"), Some("1"));
+        // Nothing past the body was consumed
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"NEXT");
+
+        let mut reader = RequestReader::from_buf_read(std::io::BufReader::with_capacity(
+            1,
+            SlowReader::new(&message[..], 1),
+        ));
+        let (body, _) = read_chunked(&mut reader, 1024).unwrap();
+        assert_eq!(body, b"hello, world, chunked!");
+    }
+
+    #[test]
+    fn chunked_reads_trailers() {
+        let (body, trailers) =
+            decode("4\r\ndata\r\n0\r\nContent-MD5: 8d777f385d3dfec8815d20f7496026dc\r\n\r\n")
+                .unwrap();
+
+        assert_eq!(body, b"data");
+        assert_eq!(
+            trailers.get("content-md5"),
+            Some("8d777f385d3dfec8815d20f7496026dc")
+        );
+    }
+
+    #[test]
+    fn chunked_errors_when_size_invalid() {
+        let e = decode("zz\r\nhello\r\n0\r\n\r\n").expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn chunked_errors_when_data_not_followed_by_crlf() {
+        let e = decode("2\r\nhello\r\n0\r\n\r\n").expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn chunked_errors_when_body_too_large() {
+        let mut reader = RequestReader::from_reader("5\r\nhello\r\n0\r\n\r\n".as_bytes());
+        let e = read_chunked(&mut reader, 4).expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::BodyTooLarge { limit: 4 }));
+    }
+
+    #[test]
+    fn chunked_rejects_absurd_chunk_size() {
+        let mut reader = RequestReader::from_reader("ffffffffffffffff\r\nhello".as_bytes());
+        let e = read_chunked(&mut reader, u64::MAX).expect_err("expected error");
+
+        assert!(matches!(
+            e,
+            RequestParsingError::ChunkTooLarge {
+                limit: MAX_CHUNK_SIZE
+            }
+        ));
+
+        let e = decode("10000000000000000\r\nhello").expect_err("expected error");
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn chunked_rejects_unbounded_extensions() {
+        let message = format!("5;ext={}\r\nhello\r\n0\r\n\r\n", "x".repeat(64 * 1024));
+        let e = decode(&message).expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+}
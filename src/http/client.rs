@@ -0,0 +1,299 @@
+//! Building requests, to send with a minimal blocking client or to pass to handlers in tests.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use super::{
+    chunked,
+    reader::{RequestReader, RequestReaderError},
+    Extensions, Headers, Method, Request, RequestParsingError, Response, StatusCode, Version,
+};
+
+/// A request to be written to a connection, the outbound counterpart of [`Request`]. It can also
+/// [`build`](RequestBuilder::build) a [`Request`] directly, to call a handler without a socket.
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    method: Method,
+    target: String,
+    version: Version,
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+impl RequestBuilder {
+    pub fn new(method: Method, target: impl Into<String>) -> Self {
+        Self {
+            method,
+            target: target.into(),
+            version: Version::V1_1,
+            headers: Headers::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Appends a header, keeping any existing header with the same name.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Serializes the request to `w`. A `Content-Length` header is added for non-empty bodies
+    /// unless one was already set.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "{} {} {}\r\n", self.method, self.target, self.version)?;
+        for (name, value) in self.headers.iter() {
+            write!(w, "{}: {}\r\n", name, value)?;
+        }
+        if !self.body.is_empty() && !self.headers.contains("Content-Length") {
+            write!(w, "Content-Length: {}\r\n", self.body.len())?;
+        }
+        w.write_all(b"\r\n")?;
+        w.write_all(&self.body)
+    }
+
+    /// Builds the [`Request`] a server would have parsed from this one, without a peer address.
+    /// An empty body gives a request without body, and no `Content-Length` header is added.
+    pub fn build(self) -> Request {
+        Request {
+            method: self.method,
+            target: self.target,
+            version: self.version,
+            headers: self.headers,
+            body: (!self.body.is_empty()).then_some(self.body),
+            trailers: Headers::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+impl Request {
+    /// Starts building a request in code, see [`RequestBuilder::build`].
+    pub fn builder(method: Method, target: impl Into<String>) -> RequestBuilder {
+        RequestBuilder::new(method, target)
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    /// The server's response couldn't be parsed.
+    InvalidResponse,
+    /// The response has a status code not known to [`StatusCode`].
+    UnknownStatus(u16),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error sending request: {}", e),
+            Self::InvalidResponse => write!(f, "invalid response"),
+            Self::UnknownStatus(code) => write!(f, "unknown response status {}", code),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<RequestReaderError> for ClientError {
+    fn from(value: RequestReaderError) -> Self {
+        match value {
+            RequestReaderError::Io(e) => Self::Io(e),
+            _ => Self::InvalidResponse,
+        }
+    }
+}
+
+impl From<RequestParsingError> for ClientError {
+    fn from(value: RequestParsingError) -> Self {
+        match value {
+            RequestParsingError::Io(e) => Self::Io(e),
+            _ => Self::InvalidResponse,
+        }
+    }
+}
+
+/// Connects to `addr`, sends `request` and reads back the final response. A `Host` header is
+/// added if the request doesn't have one.
+pub fn send(addr: impl ToSocketAddrs, request: &RequestBuilder) -> Result<Response, ClientError> {
+    let mut stream = TcpStream::connect(addr)?;
+    if request.headers.contains("Host") {
+        request.write_to(&mut stream)?;
+    } else {
+        let host = stream.peer_addr()?.to_string();
+        request
+            .clone()
+            .with_header("Host", host)
+            .write_to(&mut stream)?;
+    }
+    stream.flush()?;
+
+    read_response(&mut RequestReader::from_reader(stream))
+}
+
+/// Reads a response, skipping any interim (1xx) responses before it. The body is delimited by
+/// chunked transfer coding, `Content-Length`, or the end of the connection.
+pub fn read_response<R: Read>(reader: &mut RequestReader<R>) -> Result<Response, ClientError> {
+    loop {
+        let status_line = reader.read_line()?;
+        let mut items = status_line.splitn(3, ' ');
+        let version = Version::try_from(items.next().unwrap_or_default())
+            .map_err(|_| ClientError::InvalidResponse)?;
+        let code: u16 = items
+            .next()
+            .and_then(|code| code.parse().ok())
+            .ok_or(ClientError::InvalidResponse)?;
+        let status = StatusCode::try_from(code).map_err(ClientError::UnknownStatus)?;
+        let headers = Headers::from_lines(&reader.read_headers()?)?;
+
+        if status.is_informational() && status != StatusCode::SwitchingProtocols {
+            continue;
+        }
+
+        let mut response = Response::new(status).with_version(version);
+        for (name, value) in headers.iter() {
+            response = response.with_header(name, value);
+        }
+
+        let is_chunked = headers
+            .get("Transfer-Encoding")
+            .is_some_and(|value| value.to_ascii_lowercase().ends_with("chunked"));
+        let body = if !status.allows_body() {
+            Vec::new()
+        } else if is_chunked {
+            chunked::read_chunked(reader, u64::MAX)?.0
+        } else if let Some(length) = headers.get("Content-Length") {
+            let length: u64 = length.parse().map_err(|_| ClientError::InvalidResponse)?;
+            let mut body = Vec::new();
+            reader.take(length).read_to_end(&mut body)?;
+            if (body.len() as u64) < length {
+                return Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()));
+            }
+            body
+        } else {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body)?;
+            body
+        };
+
+        return Ok(response.with_body(body));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::server::{Handler, Server, ServerConfig};
+
+    #[test]
+    fn request_builder_writes_request() {
+        let request = RequestBuilder::new(Method::Post, "/items")
+            .with_header("Host", "localhost")
+            .with_body("hello");
+        let mut out = Vec::new();
+        request.write_to(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "POST /items HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello"
+        );
+    }
+
+    #[test]
+    fn request_builder_builds_request_for_handlers() {
+        let request = Request::builder(Method::Post, "/items?page=2")
+            .with_header("Host", "localhost")
+            .with_body("hello")
+            .build();
+
+        assert_eq!(request.method(), Method::Post);
+        assert_eq!(request.target(), "/items?page=2");
+        assert_eq!(request.version(), Version::V1_1);
+        assert_eq!(request.host(), Some("localhost"));
+        assert_eq!(request.body_bytes(), Some("hello".as_bytes()));
+
+        let handler = |request: &Request| {
+            Response::new(StatusCode::Ok).with_body(request.body_bytes().unwrap_or_default())
+        };
+        assert_eq!(handler.handle(&request).unwrap().body(), b"hello");
+        assert!(Request::builder(Method::Get, "/")
+            .build()
+            .body_bytes()
+            .is_none());
+    }
+
+    #[test]
+    fn read_response_skips_interim_responses() {
+        let message = "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let response = read_response(&mut RequestReader::from_reader(message.as_bytes()))
+            .expect("error reading response");
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.body(), b"ok");
+    }
+
+    #[test]
+    fn read_response_reads_chunked_and_close_delimited_bodies() {
+        let chunked = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n";
+        let response = read_response(&mut RequestReader::from_reader(chunked.as_bytes())).unwrap();
+        assert_eq!(response.body(), b"ok");
+
+        let close = "HTTP/1.0 200 OK\r\n\r\nuntil the end";
+        let response = read_response(&mut RequestReader::from_reader(close.as_bytes())).unwrap();
+        assert_eq!(response.body(), b"until the end");
+    }
+
+    #[test]
+    fn read_response_errors_when_status_line_invalid() {
+        let message = "HTTP/1.1 abc OK\r\n\r\n";
+        let e = read_response(&mut RequestReader::from_reader(message.as_bytes()))
+            .expect_err("expected error");
+
+        assert!(matches!(e, ClientError::InvalidResponse));
+    }
+
+    #[test]
+    fn client_round_trips_with_server() {
+        let server = Server::bind(ServerConfig::with_addr("127.0.0.1:0".parse().unwrap()))
+            .expect("error binding server");
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle().unwrap();
+        thread::spawn(move || {
+            server.serve(|request: &Request| {
+                Response::new(StatusCode::Ok).with_body(request.target().to_owned())
+            })
+        });
+
+        let response = send(addr, &RequestBuilder::new(Method::Get, "/hello")).unwrap();
+        handle.shutdown();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.body(), b"/hello");
+    }
+}
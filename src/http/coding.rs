@@ -0,0 +1,181 @@
+//! Content codings, such as `gzip`, negotiated through the `Accept-Encoding` header
+//! (RFC 7231 §5.3.4).
+
+use super::Request;
+
+/// The coding leaving content as is, always supported.
+pub const IDENTITY: &str = "identity";
+
+/// A coding from an `Accept-Encoding` header, such as `gzip;q=0.8` or `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodingRange {
+    coding: String,
+    quality: f32,
+}
+
+impl CodingRange {
+    /// Lowercased coding name, `*` for any coding not listed otherwise.
+    pub fn coding(&self) -> &str {
+        &self.coding
+    }
+
+    /// Preference weight between 0 and 1, where 0 means "not acceptable".
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let mut params = value.split(';');
+        let coding = params.next()?.trim();
+        if coding.is_empty() {
+            return None;
+        }
+
+        let mut quality = 1.0;
+        for param in params {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            if name.trim().eq_ignore_ascii_case("q") {
+                quality = value.trim().parse().ok()?;
+                if !(0.0..=1.0).contains(&quality) {
+                    return None;
+                }
+            }
+        }
+
+        Some(Self {
+            coding: coding.to_ascii_lowercase(),
+            quality,
+        })
+    }
+}
+
+impl Request {
+    /// Codings from the `Accept-Encoding` header, most preferred first. Malformed entries are
+    /// skipped. A missing header gives an empty list, as does an empty one, which only accepts
+    /// [`IDENTITY`].
+    pub fn accept_encoding(&self) -> Vec<CodingRange> {
+        let mut ranges: Vec<_> = self
+            .headers()
+            .get_all("Accept-Encoding")
+            .flat_map(|value| value.split(','))
+            .filter_map(CodingRange::parse)
+            .collect();
+        // Stable, so equally ranked entries keep the client's order
+        ranges.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+        ranges
+    }
+
+    /// Picks the coding to send the response in among `supported`, listed in the server's order
+    /// of preference, or [`IDENTITY`] to send it as is.
+    ///
+    /// The client's highest quality coding wins, ties going to the server's preference. Codings
+    /// the client doesn't list are only acceptable through `*`, except `identity` which is
+    /// acceptable unless excluded with `identity;q=0` or `*;q=0`. `None` means no coding is
+    /// acceptable, to be answered with `406 Not Acceptable`.
+    pub fn negotiate_encoding<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        let ranges = self.accept_encoding();
+        let quality_of = |coding: &str| {
+            let listed = |name: &str| ranges.iter().find(|range| range.coding == name);
+            listed(coding)
+                .or_else(|| listed("*"))
+                .map(CodingRange::quality)
+        };
+
+        let mut best: Option<(&'a str, f32)> = None;
+        let candidates = supported
+            .iter()
+            .copied()
+            .filter(|coding| !coding.eq_ignore_ascii_case(IDENTITY))
+            .map(|coding| {
+                (
+                    coding,
+                    quality_of(&coding.to_ascii_lowercase()).unwrap_or(0.0),
+                )
+            })
+            // Unlisted, identity still ranks after every coding the client explicitly accepts
+            .chain([(IDENTITY, quality_of(IDENTITY).unwrap_or(f32::MIN_POSITIVE))]);
+        for (coding, quality) in candidates {
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((coding, quality));
+            }
+        }
+        best.map(|(coding, _)| coding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    fn negotiate(accept_encoding: Option<&str>) -> Option<&'static str> {
+        let mut builder = Request::builder(Method::Get, "/");
+        if let Some(value) = accept_encoding {
+            builder = builder.with_header("Accept-Encoding", value);
+        }
+        builder
+            .build()
+            .negotiate_encoding(&["br", "gzip", "deflate"])
+    }
+
+    #[test]
+    fn accept_encoding_ranks_by_quality() {
+        let request = Request::builder(Method::Get, "/")
+            .with_header(
+                "Accept-Encoding",
+                "gzip;q=0.8, BR, deflate;q=0.5, bad;q=2, identity;q=0",
+            )
+            .build();
+
+        let ranges: Vec<_> = request
+            .accept_encoding()
+            .iter()
+            .map(|range| (range.coding().to_owned(), range.quality()))
+            .collect();
+
+        assert_eq!(
+            ranges,
+            [
+                ("br".to_owned(), 1.0),
+                ("gzip".to_owned(), 0.8),
+                ("deflate".to_owned(), 0.5),
+                ("identity".to_owned(), 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn negotiate_encoding_follows_q_values() {
+        assert_eq!(
+            negotiate(Some("gzip;q=0.8, deflate;q=0.9")),
+            Some("deflate")
+        );
+        assert_eq!(negotiate(Some("gzip;q=0.8, br;q=0")), Some("gzip"));
+        // Equal qualities go to the server's preference
+        assert_eq!(negotiate(Some("deflate, gzip")), Some("gzip"));
+        assert_eq!(negotiate(Some("*;q=0.5, gzip;q=0.1")), Some("br"));
+    }
+
+    #[test]
+    fn negotiate_encoding_defaults_to_identity() {
+        assert_eq!(negotiate(None), Some(IDENTITY));
+        assert_eq!(negotiate(Some("")), Some(IDENTITY));
+        assert_eq!(negotiate(Some("zstd, compress")), Some(IDENTITY));
+        assert_eq!(negotiate(Some("identity;q=1, gzip;q=0.5")), Some(IDENTITY));
+    }
+
+    #[test]
+    fn negotiate_encoding_honors_identity_q_zero() {
+        assert_eq!(negotiate(Some("gzip;q=0.2, identity;q=0")), Some("gzip"));
+        assert_eq!(negotiate(Some("*;q=0, deflate")), Some("deflate"));
+    }
+
+    #[test]
+    fn negotiate_encoding_fails_when_nothing_is_acceptable() {
+        assert_eq!(negotiate(Some("zstd, identity;q=0")), None);
+        assert_eq!(negotiate(Some("*;q=0")), None);
+        assert_eq!(negotiate(Some("gzip;q=0, identity;q=0")), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Conditional `GET` requests (RFC 7232), answered with `304 Not Modified` when the client's
+//! cached copy is still current.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{date, Method, Request, Response, StatusCode};
+
+impl Request {
+    /// Entity tags listed in `If-None-Match`, with their quotes and any `W/` prefix. `*` is
+    /// returned as-is. `None` if the header is missing.
+    pub fn if_none_match(&self) -> Option<Vec<&str>> {
+        self.header("If-None-Match").map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .collect()
+        })
+    }
+
+    /// Date in `If-Modified-Since`, `None` if the header is missing or isn't a valid date.
+    pub fn if_modified_since(&self) -> Option<SystemTime> {
+        self.header("If-Modified-Since").and_then(date::parse)
+    }
+
+    /// Whether the client's cached copy of a resource with the given entity tag and modification
+    /// time is current, so a `304 Not Modified` can be sent instead of the resource.
+    ///
+    /// `If-None-Match` takes precedence over `If-Modified-Since`, and only `GET` and `HEAD`
+    /// requests can be answered with a 304.
+    pub fn is_not_modified(&self, etag: Option<&str>, last_modified: Option<SystemTime>) -> bool {
+        if !matches!(self.method(), Method::Get | Method::Head) {
+            return false;
+        }
+
+        if let Some(tags) = self.if_none_match() {
+            // Weak comparison, `W/"a"` matches `"a"`
+            let strip_weak = |tag: &str| tag.strip_prefix("W/").unwrap_or(tag).to_owned();
+            return etag.is_some_and(|etag| {
+                let etag = strip_weak(etag);
+                tags.iter()
+                    .any(|&tag| tag == "*" || strip_weak(tag) == etag)
+            });
+        }
+
+        match (self.if_modified_since(), last_modified) {
+            (Some(since), Some(modified)) => truncate_to_secs(modified) <= since,
+            _ => false,
+        }
+    }
+}
+
+/// HTTP dates have a one second resolution, sub-second parts would always compare as newer.
+pub(super) fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since_epoch) => UNIX_EPOCH + Duration::from_secs(since_epoch.as_secs()),
+        Err(_) => time,
+    }
+}
+
+impl Response {
+    /// A `304 Not Modified` response repeating the resource's entity tag, if it has one.
+    pub fn not_modified(etag: Option<&str>) -> Self {
+        let response = Self::new(StatusCode::NotModified);
+        match etag {
+            Some(etag) => response.with_header("ETag", etag),
+            None => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, header: &str) -> Request {
+        let message = format!(
+            "{} /file HTTP/1.1\r\nHost: localhost\r\n{}\r\n\r\n",
+            method, header
+        );
+        Request::parse(message.as_bytes()).expect("error parsing request")
+    }
+
+    #[test]
+    fn if_none_match_lists_tags() {
+        let request = request("GET", "If-None-Match: \"a\", W/\"b\"");
+
+        assert_eq!(request.if_none_match(), Some(vec!["\"a\"", "W/\"b\""]));
+    }
+
+    #[test]
+    fn not_modified_when_etag_matches() {
+        let request = request("GET", "If-None-Match: \"v1\", \"v2\"");
+
+        assert!(request.is_not_modified(Some("\"v2\""), None));
+        assert!(request.is_not_modified(Some("W/\"v1\""), None));
+    }
+
+    #[test]
+    fn modified_when_etag_differs() {
+        let request = request("GET", "If-None-Match: \"v1\"");
+
+        assert!(!request.is_not_modified(Some("\"v3\""), None));
+        assert!(!request.is_not_modified(None, None));
+    }
+
+    #[test]
+    fn if_modified_since_compares_dates() {
+        let request = request("GET", "If-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT");
+        let since = UNIX_EPOCH + Duration::from_secs(784_111_777);
+
+        assert_eq!(request.if_modified_since(), Some(since));
+        assert!(request.is_not_modified(None, Some(since + Duration::from_millis(500))));
+        assert!(!request.is_not_modified(None, Some(since + Duration::from_secs(1))));
+    }
+
+    #[test]
+    fn only_get_and_head_can_be_not_modified() {
+        let request = request("POST", "If-None-Match: *");
+
+        assert!(!request.is_not_modified(Some("\"v1\""), None));
+    }
+
+    #[test]
+    fn not_modified_response_repeats_etag() {
+        let response = Response::not_modified(Some("\"v1\""));
+
+        assert_eq!(response.status(), StatusCode::NotModified);
+        assert_eq!(response.header("ETag"), Some("\"v1\""));
+        assert!(response.body().is_empty());
+    }
+}
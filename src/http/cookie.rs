@@ -0,0 +1,183 @@
+//! `Cookie` request header parsing and `Set-Cookie` response headers (RFC 6265).
+
+use std::{collections::HashMap, fmt::Display, time::Duration};
+
+use super::{encoding::percent_decode, Request, Response};
+
+impl Request {
+    /// Cookies sent in the `Cookie` header, by name. Values are percent-decoded when they contain
+    /// valid escapes, and kept as-is otherwise. Returns an empty map if the header is missing.
+    pub fn cookies(&self) -> HashMap<String, String> {
+        let Some(header) = self.header("Cookie") else {
+            return HashMap::new();
+        };
+
+        header
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                let value = value.trim();
+                let value = percent_decode(value).unwrap_or_else(|| value.to_owned());
+                Some((name.to_owned(), value))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "Strict"),
+            Self::Lax => write!(f, "Lax"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
+
+/// A cookie to send to the client in a `Set-Cookie` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    max_age: Option<Duration>,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            max_age: None,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets `Max-Age`, in whole seconds. A zero duration tells the client to delete the cookie.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+impl Display for SetCookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        Ok(())
+    }
+}
+
+impl Response {
+    /// Appends a `Set-Cookie` header, keeping any cookie already set on the response.
+    pub fn with_cookie(self, cookie: &SetCookie) -> Self {
+        self.with_header("Set-Cookie", cookie.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{reader::RequestReader, StatusCode};
+
+    fn request(cookie: Option<&str>) -> Request {
+        let mut message = String::from("GET / HTTP/1.1\r\nHost: localhost\r\n");
+        if let Some(cookie) = cookie {
+            message.push_str(&format!("Cookie: {}\r\n", cookie));
+        }
+        message.push_str("\r\n");
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        Request::try_from_reader(&mut reader).expect("error parsing request")
+    }
+
+    #[test]
+    fn cookies_are_split_into_pairs() {
+        let cookies = request(Some("session=abc123; theme=dark")).cookies();
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies["session"], "abc123");
+        assert_eq!(cookies["theme"], "dark");
+    }
+
+    #[test]
+    fn cookies_tolerate_whitespace_and_decode_values() {
+        let cookies = request(Some("name = John%20Doe ;  empty=;bad%zz=%zz")).cookies();
+
+        assert_eq!(cookies["name"], "John Doe");
+        assert_eq!(cookies["empty"], "");
+        assert_eq!(cookies["bad%zz"], "%zz");
+    }
+
+    #[test]
+    fn cookies_are_empty_when_header_missing_or_empty() {
+        assert!(request(None).cookies().is_empty());
+        assert!(request(Some("")).cookies().is_empty());
+    }
+
+    #[test]
+    fn set_cookie_formats_attributes() {
+        let cookie = SetCookie::new("session", "abc123")
+            .with_path("/")
+            .with_max_age(Duration::from_secs(3600))
+            .http_only()
+            .with_same_site(SameSite::Lax);
+
+        assert_eq!(
+            cookie.to_string(),
+            "session=abc123; Path=/; Max-Age=3600; HttpOnly; SameSite=Lax"
+        );
+    }
+
+    #[test]
+    fn response_appends_set_cookie_headers() {
+        let response = Response::new(StatusCode::Ok)
+            .with_cookie(&SetCookie::new("a", "1"))
+            .with_cookie(&SetCookie::new("b", "2"));
+
+        assert_eq!(
+            response.headers().get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+    }
+}
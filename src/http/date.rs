@@ -0,0 +1,141 @@
+//! IMF-fixdate timestamps (RFC 7231 §7.1.1.1), as used by the `Date` header.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats `time` as e.g. `Sun, 06 Nov 1994 08:49:37 GMT`. Times before the Unix epoch are
+/// formatted as the epoch.
+pub fn format(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let days = secs / 86400;
+    let secs_of_day = secs % 86400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Parses an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT`. The obsolete RFC 850 and
+/// asctime formats aren't supported.
+pub fn parse(value: &str) -> Option<SystemTime> {
+    let (weekday, rest) = value.trim().split_once(", ")?;
+    if !DAYS.contains(&weekday) {
+        return None;
+    }
+
+    let mut parts = rest.split(' ');
+    let day: u64 = parse_digits(parts.next()?, 2)?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|&m| m == month)? as u64 + 1;
+    let year: u64 = parse_digits(parts.next()?, 4)?;
+    let mut time = parts.next()?.split(':');
+    let hour = parse_digits(time.next()?, 2)?;
+    let minute = parse_digits(time.next()?, 2)?;
+    let second = parse_digits(time.next()?, 2)?;
+    if parts.next()? != "GMT" || parts.next().is_some() || time.next().is_some() {
+        return None;
+    }
+    if year < 1970 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    // Reject dates like Feb 30th, which would silently roll over into the next month
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn parse_digits(value: &str, len: usize) -> Option<u64> {
+    if value.len() != len || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Inverse of [`civil_from_days`], for dates from 1970 onwards.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Converts days since the Unix epoch to a `(year, month, day)` date in the proleptic Gregorian
+/// calendar, with months and days starting at 1.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days, shifted so eras start
+    // on March 1st and leap days fall at the end of a year
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn format_matches_rfc_example() {
+        assert_eq!(format(at(784_111_777)), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn format_handles_epoch_and_leap_days() {
+        assert_eq!(format(at(0)), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(format(at(951_782_400)), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(format(at(1_709_251_199)), "Thu, 29 Feb 2024 23:59:59 GMT");
+    }
+
+    #[test]
+    fn parse_reads_format_output() {
+        for secs in [0, 784_111_777, 951_782_400, 1_709_251_199] {
+            assert_eq!(parse(&format(at(secs))), Some(at(secs)));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_dates() {
+        for value in [
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 6 Nov 1994 08:49:37 GMT",
+            "Fri, 30 Feb 2024 00:00:00 GMT",
+            "Sun, 06 Nov 1994 25:49:37 GMT",
+        ] {
+            assert_eq!(parse(value), None, "{}", value);
+        }
+    }
+}
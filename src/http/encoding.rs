@@ -0,0 +1,104 @@
+//! Percent-encoding (RFC 3986 §2.1), to decode targets and form values and to build `Location`
+//! headers or outbound request targets.
+
+/// Escapes every byte of `input` outside the unreserved set (letters, digits, `-`, `.`, `_` and
+/// `~`) as `%XX`, with multibyte characters escaped byte by byte.
+pub fn percent_encode(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            output.push(b as char);
+        } else {
+            output.push_str(&format!("%{:02X}", b));
+        }
+    }
+    output
+}
+
+/// Escapes the bytes that can't appear in a URI, keeping reserved characters such as `/` and `?`
+/// and existing `%XX` escapes, to turn a path or URL into a valid target or `Location`.
+pub fn percent_encode_uri(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if is_uri_char(b) {
+            output.push(b as char);
+        } else {
+            output.push_str(&format!("%{:02X}", b));
+        }
+    }
+    output
+}
+
+/// Characters allowed in a URI (RFC 3986 §2): unreserved, reserved and `%` for percent-encoding.
+pub(crate) fn is_uri_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=%".contains(&b)
+}
+
+/// Decodes `%XX` escapes, returning `None` if an escape is malformed or the result isn't UTF-8.
+pub fn percent_decode(input: &str) -> Option<String> {
+    let input = input.as_bytes();
+    let mut output = Vec::with_capacity(input.len());
+    let mut idx = 0;
+    while idx < input.len() {
+        if input[idx] == b'%' {
+            let hex = input.get(idx + 1..idx + 3)?;
+            let hex = std::str::from_utf8(hex).ok()?;
+            output.push(u8::from_str_radix(hex, 16).ok()?);
+            idx += 3;
+        } else {
+            output.push(input[idx]);
+            idx += 1;
+        }
+    }
+    String::from_utf8(output).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_replaces_escapes() {
+        assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+        assert_eq!(percent_decode("%C3%A9").as_deref(), Some("é"));
+        assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn decode_rejects_malformed_escapes() {
+        assert_eq!(percent_decode("%"), None);
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%ff"), None);
+    }
+
+    #[test]
+    fn encode_escapes_reserved_characters() {
+        assert_eq!(percent_encode("a b"), "a%20b");
+        assert_eq!(percent_encode("é"), "%C3%A9");
+        assert_eq!(percent_encode("a/b?c=d&e"), "a%2Fb%3Fc%3Dd%26e");
+        assert_eq!(percent_encode("AZaz09-._~"), "AZaz09-._~");
+    }
+
+    #[test]
+    fn encode_uri_keeps_reserved_characters() {
+        assert_eq!(percent_encode_uri("/a b/?q=1&r=%2F"), "/a%20b/?q=1&r=%2F");
+        assert_eq!(percent_encode_uri("/\"é\""), "/%22%C3%A9%22");
+    }
+
+    #[test]
+    fn encode_round_trips_through_decode() {
+        for input in [
+            "",
+            "plain",
+            "50% off",
+            "un café, s'il vous plaît",
+            "日本語/ü",
+        ] {
+            assert_eq!(
+                percent_decode(&percent_encode(input)).as_deref(),
+                Some(input)
+            );
+        }
+    }
+}
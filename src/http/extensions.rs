@@ -0,0 +1,91 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+};
+
+/// Typed values attached to a request while it is processed, such as the authenticated user found
+/// by an earlier handler. Holds at most one value per type.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value`, returning the value of the same type it replaced.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok().map(|b| *b))
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    pub fn get_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|b| *b))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Values aren't required to implement Debug
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User(&'static str);
+
+    #[test]
+    fn extensions_store_one_value_per_type() {
+        let mut extensions = Extensions::new();
+        assert_eq!(extensions.insert(User("alice")), None);
+        assert_eq!(extensions.insert(42u32), None);
+
+        assert_eq!(extensions.get::<User>(), Some(&User("alice")));
+        assert_eq!(extensions.get::<u32>(), Some(&42));
+        assert_eq!(extensions.get::<u64>(), None);
+        assert_eq!(extensions.insert(User("bob")), Some(User("alice")));
+        assert_eq!(extensions.len(), 2);
+    }
+
+    #[test]
+    fn extensions_get_mut_and_remove() {
+        let mut extensions = Extensions::new();
+        extensions.insert(Vec::<u8>::new());
+        extensions.get_mut::<Vec<u8>>().unwrap().push(1);
+
+        assert_eq!(extensions.remove::<Vec<u8>>(), Some(vec![1]));
+        assert!(extensions.is_empty());
+    }
+}
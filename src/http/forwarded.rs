@@ -0,0 +1,344 @@
+//! Client addresses behind reverse proxies, from the `Forwarded` (RFC 7239) and
+//! `X-Forwarded-For` headers.
+
+use std::net::IpAddr;
+
+use super::Request;
+
+/// A range of addresses, in CIDR notation such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    /// Network of the addresses sharing the first `prefix` bits of `addr`. Returns `None` if
+    /// `prefix` is longer than the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Self> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    /// Parses `addr/prefix`, or a single address as a network containing only it.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.split_once('/') {
+            Some((addr, prefix)) => Self::new(addr.parse().ok()?, prefix.parse().ok()?),
+            None => {
+                let addr: IpAddr = value.parse().ok()?;
+                Self::new(addr, if addr.is_ipv4() { 32 } else { 128 })
+            }
+        }
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// One forwarding element of a `Forwarded` header, describing a hop through a proxy. Values are
+/// unquoted, and node identifiers such as `for` are kept as sent: an address with an optional
+/// port (`192.0.2.60`, `[2001:db8::17]:4711`), `unknown` or an obfuscated `_hidden` name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardedElement {
+    forwarded_for: Option<String>,
+    by: Option<String>,
+    host: Option<String>,
+    proto: Option<String>,
+}
+
+impl ForwardedElement {
+    /// The client the proxy received the request from, the `for` parameter.
+    pub fn forwarded_for(&self) -> Option<&str> {
+        self.forwarded_for.as_deref()
+    }
+
+    /// The proxy's own interface the request came in on, the `by` parameter.
+    pub fn by(&self) -> Option<&str> {
+        self.by.as_deref()
+    }
+
+    /// The `Host` the proxy received.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// The protocol the proxy was contacted with, such as `https`.
+    pub fn proto(&self) -> Option<&str> {
+        self.proto.as_deref()
+    }
+
+    /// Address in the `for` parameter, without its port. `None` for a missing parameter, an
+    /// `unknown` or obfuscated node, or one that doesn't parse.
+    pub fn for_ip(&self) -> Option<IpAddr> {
+        node_ip(self.forwarded_for.as_deref()?)
+    }
+
+    /// Parses one element, `None` if any of its pairs is malformed. Unknown parameters are
+    /// ignored.
+    fn parse(element: &str) -> Option<Self> {
+        let mut parsed = Self::default();
+        for pair in split_unquoted(element, ';') {
+            let (name, value) = pair.split_once('=')?;
+            let value = unquote(value.trim())?;
+            let slot = match name.trim().to_ascii_lowercase().as_str() {
+                "for" => &mut parsed.forwarded_for,
+                "by" => &mut parsed.by,
+                "host" => &mut parsed.host,
+                "proto" => &mut parsed.proto,
+                _ => continue,
+            };
+            *slot = Some(value);
+        }
+        Some(parsed)
+    }
+}
+
+/// Address of a node identifier, like `192.0.2.60:80` or `[2001:db8::17]`.
+fn node_ip(node: &str) -> Option<IpAddr> {
+    if let Some(rest) = node.strip_prefix('[') {
+        let (addr, _port) = rest.split_once(']')?;
+        return addr.parse().ok();
+    }
+    let addr = node.split_once(':').map_or(node, |(addr, _port)| addr);
+    addr.parse().ok()
+}
+
+/// Splits `value` on `separator`, except within quoted strings, skipping empty parts.
+fn split_unquoted(value: &str, separator: char) -> impl Iterator<Item = &str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().filter(|part| !part.trim().is_empty())
+}
+
+/// Value of a parameter, removing the quotes and escapes of a quoted string. `None` for an
+/// unterminated quoted string.
+fn unquote(value: &str) -> Option<String> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        return Some(value.to_owned());
+    };
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.push(chars.next()?),
+            '"' => return chars.as_str().is_empty().then_some(unquoted),
+            c => unquoted.push(c),
+        }
+    }
+    None
+}
+
+impl Request {
+    /// Forwarding elements of every `Forwarded` header, from the client's side to the closest
+    /// proxy. Malformed elements are skipped.
+    pub fn forwarded(&self) -> Vec<ForwardedElement> {
+        self.headers()
+            .get_all("Forwarded")
+            .flat_map(|value| split_unquoted(value, ','))
+            .filter_map(ForwardedElement::parse)
+            .collect()
+    }
+
+    /// Address of the client, looking through `trusted_proxies`. The `for` parameters of
+    /// `Forwarded`, or `X-Forwarded-For` without it, are only consulted when the peer is a
+    /// trusted proxy, and are walked from the right, where the closest proxy appended its own
+    /// peer: the first untrusted address is the client. Anything left of it could have been
+    /// sent by the client and is ignored.
+    ///
+    /// Returns `None` for connections without a peer address, such as Unix sockets.
+    pub fn client_ip(&self, trusted_proxies: &[IpNetwork]) -> Option<IpAddr> {
+        let is_trusted = |addr: IpAddr| trusted_proxies.iter().any(|n| n.contains(addr));
+        let mut client = self.peer_addr()?.ip();
+
+        let hops: Vec<Option<IpAddr>> = if self.headers().contains("Forwarded") {
+            // Hidden or unknown clients can't be told apart from a malformed element
+            self.headers()
+                .get_all("Forwarded")
+                .flat_map(|value| split_unquoted(value, ','))
+                .map(|element| ForwardedElement::parse(element)?.for_ip())
+                .collect()
+        } else {
+            self.headers()
+                .get_all("X-Forwarded-For")
+                .flat_map(|value| value.split(','))
+                .map(|hop| hop.trim().parse().ok())
+                .collect()
+        };
+        for hop in hops.iter().rev() {
+            if !is_trusted(client) {
+                break;
+            }
+            // A malformed entry can't be trusted to name the client, stop at the last proxy
+            let Some(addr) = *hop else {
+                break;
+            };
+            client = addr;
+        }
+        Some(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer: &str, forwarded_for: &[&str]) -> Request {
+        request_with(peer, "X-Forwarded-For", forwarded_for)
+    }
+
+    fn request_with(peer: &str, header: &str, values: &[&str]) -> Request {
+        let mut message = "GET / HTTP/1.1\r\nHost: localhost\r\n".to_owned();
+        for value in values {
+            message.push_str(&format!("{}: {}\r\n", header, value));
+        }
+        message.push_str("\r\n");
+        let mut request = Request::parse(message.as_bytes()).unwrap();
+        request.set_peer_addr(peer.parse().unwrap());
+        request
+    }
+
+    fn networks(values: &[&str]) -> Vec<IpNetwork> {
+        values
+            .iter()
+            .map(|v| IpNetwork::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn network_contains_addresses_under_prefix() {
+        let network = IpNetwork::parse("10.1.0.0/16").unwrap();
+        assert!(network.contains("10.1.200.3".parse().unwrap()));
+        assert!(!network.contains("10.2.0.1".parse().unwrap()));
+        assert!(!network.contains("::1".parse().unwrap()));
+
+        assert!(IpNetwork::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert!(IpNetwork::parse("fd00::/8")
+            .unwrap()
+            .contains("fd12::1".parse().unwrap()));
+        assert_eq!(IpNetwork::parse("10.0.0.0/33"), None);
+        assert_eq!(IpNetwork::parse("nope"), None);
+    }
+
+    #[test]
+    fn client_ip_is_peer_when_peer_untrusted() {
+        let request = request("203.0.113.7:5000", &["1.2.3.4"]);
+
+        assert_eq!(
+            request.client_ip(&networks(&["10.0.0.0/8"])),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn client_ip_walks_trusted_proxies_from_the_right() {
+        let request = request("10.0.0.1:5000", &["6.6.6.6, 198.51.100.2", "10.0.0.2"]);
+
+        // The client prepended 6.6.6.6 itself, only the address appended by a proxy counts
+        assert_eq!(
+            request.client_ip(&networks(&["10.0.0.0/8"])),
+            Some("198.51.100.2".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn client_ip_stops_at_malformed_entries() {
+        let request = request("10.0.0.1:5000", &["198.51.100.2, garbage"]);
+
+        assert_eq!(
+            request.client_ip(&networks(&["10.0.0.0/8"])),
+            Some("10.0.0.1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn forwarded_parses_every_hop() {
+        let request = request_with(
+            "10.0.0.1:5000",
+            "Forwarded",
+            &[
+                "for=192.0.2.60;proto=http;by=203.0.113.43, For=\"[2001:db8:cafe::17]:4711\"",
+                "for=_hidden;host=\"example.com\";by=unknown",
+            ],
+        );
+
+        let forwarded = request.forwarded();
+        assert_eq!(forwarded.len(), 3);
+        assert_eq!(forwarded[0].forwarded_for(), Some("192.0.2.60"));
+        assert_eq!(forwarded[0].proto(), Some("http"));
+        assert_eq!(forwarded[0].by(), Some("203.0.113.43"));
+        assert_eq!(forwarded[0].host(), None);
+        assert_eq!(
+            forwarded[1].for_ip(),
+            Some("2001:db8:cafe::17".parse().unwrap())
+        );
+        assert_eq!(forwarded[2].forwarded_for(), Some("_hidden"));
+        assert_eq!(forwarded[2].for_ip(), None);
+        assert_eq!(forwarded[2].host(), Some("example.com"));
+        assert_eq!(forwarded[2].by(), Some("unknown"));
+    }
+
+    #[test]
+    fn forwarded_handles_quoted_values() {
+        let request = request_with(
+            "10.0.0.1:5000",
+            "Forwarded",
+            &["for=\"a,b;c\\\"d\";proto=https, for=\"unterminated, for=198.51.100.2:80"],
+        );
+
+        let forwarded = request.forwarded();
+        // Separators within quotes don't split, the unterminated quote swallows the rest
+        assert_eq!(forwarded.len(), 1);
+        assert_eq!(forwarded[0].forwarded_for(), Some("a,b;c\"d"));
+        assert_eq!(forwarded[0].proto(), Some("https"));
+    }
+
+    #[test]
+    fn client_ip_follows_forwarded_header() {
+        let trusted = networks(&["10.0.0.0/8"]);
+        let request = request_with(
+            "10.0.0.1:5000",
+            "Forwarded",
+            &[
+                "for=6.6.6.6, for=198.51.100.2;proto=https",
+                "for=\"10.0.0.2:8080\"",
+            ],
+        );
+        assert_eq!(
+            request.client_ip(&trusted),
+            Some("198.51.100.2".parse().unwrap())
+        );
+
+        // An obfuscated client hides it, the closest proxy is all that is known
+        let request = request_with("10.0.0.1:5000", "Forwarded", &["for=_hidden"]);
+        assert_eq!(
+            request.client_ip(&trusted),
+            Some("10.0.0.1".parse().unwrap())
+        );
+    }
+}
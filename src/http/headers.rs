@@ -0,0 +1,308 @@
+use super::RequestParsingError;
+
+/// Header fields of a request or response, kept in the order they were received or added with
+/// their original casing. Lookups by name are case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    entries: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `name: value` lines, as read from a request head.
+    pub fn from_lines<S: AsRef<str>>(lines: &[S]) -> Result<Self, RequestParsingError> {
+        let entries = lines
+            .iter()
+            .map(|line| {
+                let (name, value) = parse_line(line.as_ref())?;
+                Ok((name.to_owned(), value.to_owned()))
+            })
+            .collect::<Result<_, RequestParsingError>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the value of the first header matching `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the values of every header matching `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the values of every header matching `name` joined with `, `, which RFC 7230 §3.2.2
+    /// defines as equivalent to a single header. `Set-Cookie` can't be combined, as its values may
+    /// themselves hold commas, so only its first value is returned: use
+    /// [`Headers::get_all`] for it.
+    pub fn get_combined(&self, name: &str) -> Option<String> {
+        if name.eq_ignore_ascii_case("Set-Cookie") {
+            return self.get(name).map(ToOwned::to_owned);
+        }
+
+        let values: Vec<_> = self.get_all(name).collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Iterates over `(name, value)` pairs in the order they were received or added, with names in
+    /// their original casing, for proxies or logs reproducing a head faithfully.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Iterates over each distinct header name once, in the casing and position of its first
+    /// occurrence, such as for a proxy deciding which headers to forward.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(i, (name, _))| {
+                !self.entries[..*i]
+                    .iter()
+                    .any(|(earlier, _)| earlier.eq_ignore_ascii_case(name))
+            })
+            .map(|(_, (name, _))| name.as_str())
+    }
+
+    /// Appends a header, keeping any existing header with the same name.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    /// Sets a header, replacing every existing header with the same name.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.remove(&name);
+        self.entries.push((name, value.into()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+}
+
+/// Splits a `name: value` header line, checking that the name is a token and that the value holds
+/// no control characters (RFC 7230 §3.2). Characters like CR or LF in a value would otherwise
+/// allow injecting headers when the value is reflected in a response.
+///
+/// Only the optional whitespace around the value, spaces and tabs, is trimmed. Other whitespace
+/// is kept and rejected as a control character.
+pub(crate) fn parse_line(line: &str) -> Result<(&str, &str), RequestParsingError> {
+    super::reject_bare_line_endings(line)?;
+    let (name, value) = line.split_once(':').ok_or(RequestParsingError::Format)?;
+    if !is_token(name) {
+        return Err(RequestParsingError::InvalidHeaderName(name.to_owned()));
+    }
+    let value = value.trim_matches([' ', '\t']);
+    if !is_valid_value(value) {
+        return Err(RequestParsingError::InvalidHeaderValue(name.to_owned()));
+    }
+    Ok((name, value))
+}
+
+/// Whether `s` is a non-empty `token`, the grammar of header names and methods.
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Header values may hold any visible character, spaces, tabs and obsolete non-ASCII text, but no
+/// other control characters.
+fn is_valid_value(s: &str) -> bool {
+    s.bytes().all(|b| b == b'\t' || !b.is_ascii_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_parse_lines() {
+        let headers =
+            Headers::from_lines(&["Host: localhost", "Accept:*/*"]).expect("error parsing headers");
+
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.get("host"), Some("localhost"));
+        assert_eq!(headers.get("ACCEPT"), Some("*/*"));
+        assert_eq!(headers.get("Cookie"), None);
+    }
+
+    #[test]
+    fn headers_trim_optional_whitespace_around_values() {
+        let headers = Headers::from_lines(&[
+            "Host: 127.0.0.1:8080",
+            "User-Agent: \t curl/8.0 (x86_64 linux)  \t",
+        ])
+        .expect("error parsing headers");
+
+        assert_eq!(headers.get("host"), Some("127.0.0.1:8080"));
+        // Spaces inside the value are part of it
+        assert_eq!(headers.get("User-Agent"), Some("curl/8.0 (x86_64 linux)"));
+    }
+
+    #[test]
+    fn headers_error_when_value_padded_with_other_whitespace() {
+        let e = Headers::from_lines(&["X-Test: \x0bvalue"]).expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::InvalidHeaderValue(name) if name == "X-Test"));
+    }
+
+    #[test]
+    fn headers_error_when_line_has_no_colon() {
+        let e = Headers::from_lines(&["Host localhost"]).expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::Format));
+    }
+
+    #[test]
+    fn headers_get_all_returns_every_value() {
+        let headers = Headers::from_lines(&["Accept: text/html", "Host: a", "accept: */*"])
+            .expect("error parsing headers");
+
+        assert_eq!(
+            headers.get_all("Accept").collect::<Vec<_>>(),
+            ["text/html", "*/*"]
+        );
+    }
+
+    #[test]
+    fn headers_get_combined_joins_repeated_values() {
+        let headers = Headers::from_lines(&[
+            "Accept-Encoding: gzip",
+            "Host: a",
+            "accept-encoding: br, deflate",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            headers.get_combined("Accept-Encoding"),
+            Some("gzip, br, deflate".to_owned())
+        );
+        assert_eq!(headers.get_combined("Host"), Some("a".to_owned()));
+        assert_eq!(headers.get_combined("Accept"), None);
+    }
+
+    #[test]
+    fn headers_get_combined_keeps_set_cookie_separate() {
+        let mut headers = Headers::new();
+        headers.append("Set-Cookie", "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT");
+        headers.append("Set-Cookie", "b=2");
+
+        assert_eq!(
+            headers.get_combined("set-cookie"),
+            Some("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT".to_owned())
+        );
+        assert_eq!(headers.get_all("Set-Cookie").count(), 2);
+    }
+
+    #[test]
+    fn headers_names_collapse_duplicates() {
+        let headers = Headers::from_lines(&[
+            "X-Trace: 1",
+            "Host: a",
+            "x-trace: 2",
+            "ACCEPT: */*",
+            "X-TRACE: 3",
+            "accept: text/html",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            headers.names().collect::<Vec<_>>(),
+            ["X-Trace", "Host", "ACCEPT"]
+        );
+        assert_eq!(Headers::new().names().count(), 0);
+    }
+
+    #[test]
+    fn headers_iter_preserves_order_and_casing() {
+        let headers = Headers::from_lines(&[
+            "X-Trace: 1",
+            "Host: a",
+            "x-trace: 2",
+            "X-TRACE: 3",
+            "content-type: text/plain",
+        ])
+        .expect("error parsing headers");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [
+                ("X-Trace", "1"),
+                ("Host", "a"),
+                ("x-trace", "2"),
+                ("X-TRACE", "3"),
+                ("content-type", "text/plain"),
+            ]
+        );
+        assert_eq!(headers.get("X-Trace"), Some("1"));
+    }
+
+    #[test]
+    fn headers_set_replaces_existing() {
+        let mut headers = Headers::new();
+        headers.append("connection", "keep-alive");
+        headers.append("Connection", "upgrade");
+        headers.set("Connection", "close");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("Connection", "close")]
+        );
+    }
+
+    #[test]
+    fn headers_error_when_name_not_a_token() {
+        let e = Headers::from_lines(&["Bad Name: value"]).expect_err("expected error");
+
+        assert!(matches!(e, RequestParsingError::InvalidHeaderName(name) if name == "Bad Name"));
+    }
+
+    #[test]
+    fn headers_error_when_value_has_control_characters() {
+        for line in ["X-Test: a\0b", "X-Test: a\x7fb", "X-Test: a\x0bb"] {
+            let e = Headers::from_lines(&[line]).expect_err("expected error");
+
+            assert!(matches!(e, RequestParsingError::InvalidHeaderValue(name) if name == "X-Test"));
+        }
+        // Line endings have their own error, whether in the value or the name
+        for line in ["X-Test: a\nInjected: 1", "X-Test: a\rb", "X\rTest: a"] {
+            let e = Headers::from_lines(&[line]).expect_err("expected error");
+
+            assert!(matches!(e, RequestParsingError::BareLineEnding(_)));
+        }
+    }
+
+    #[test]
+    fn headers_accept_tabs_and_non_ascii_values() {
+        let headers = Headers::from_lines(&["X-Test: a\tb", "X-Name: café"]).unwrap();
+
+        assert_eq!(headers.get("X-Test"), Some("a\tb"));
+        assert_eq!(headers.get("X-Name"), Some("café"));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{error::Error, fmt::Display};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Request, Response, StatusCode};
+
+#[derive(Debug)]
+pub enum JsonError {
+    MissingBody,
+    UnexpectedContentType(Option<String>),
+    Deserialize(serde_json::Error),
+    /// A value given to [`Response::json`] couldn't be serialized, such as a map with non-string
+    /// keys.
+    Serialize(serde_json::Error),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingBody => write!(f, "request has no body"),
+            Self::UnexpectedContentType(Some(t)) => {
+                write!(f, "expected content type application/json, got {}", t)
+            }
+            Self::UnexpectedContentType(None) => {
+                write!(f, "expected content type application/json, got none")
+            }
+            Self::Deserialize(e) => write!(f, "could not deserialize the body: {}", e),
+            Self::Serialize(e) => write!(f, "could not serialize the body: {}", e),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Deserialize(value)
+    }
+}
+
+impl Request {
+    /// Deserializes the body as JSON, after checking that the request declares an
+    /// `application/json` content type.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, JsonError> {
+        let is_json = self
+            .content_type()
+            .is_some_and(|t| t.media_type() == "application/json");
+        if !is_json {
+            return Err(JsonError::UnexpectedContentType(
+                self.header("Content-Type").map(ToOwned::to_owned),
+            ));
+        }
+
+        let body = self.body_bytes().ok_or(JsonError::MissingBody)?;
+        serde_json::from_slice(body).map_err(Into::into)
+    }
+}
+
+impl Response {
+    /// A response with `value` serialized as its JSON body, and an `application/json` content
+    /// type. Handlers can return the error with `?`, answering with a 500.
+    pub fn json<T: Serialize + ?Sized>(status: StatusCode, value: &T) -> Result<Self, JsonError> {
+        let body = serde_json::to_vec(value).map_err(JsonError::Serialize)?;
+        Ok(Self::new(status)
+            .with_header("Content-Type", "application/json")
+            .with_body(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::http::{reader::RequestReader, Extensions, Headers, Method, Version};
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    fn request(headers: &[&str], body: Option<&str>) -> Request {
+        Request {
+            method: Method::Post,
+            target: "/".to_owned(),
+            version: Version::V1_1,
+            headers: Headers::from_lines(headers).unwrap(),
+            body: body.map(|b| b.as_bytes().to_vec()),
+            trailers: Headers::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
+        }
+    }
+
+    #[test]
+    fn json_deserializes_body() {
+        let request = request(
+            &["Content-Type: application/json; charset=utf-8"],
+            Some(r#"{"x": 1, "y": -2}"#),
+        );
+        let point: Point = request.json().expect("error deserializing body");
+
+        assert_eq!(point, Point { x: 1, y: -2 });
+    }
+
+    #[test]
+    fn json_errors_when_wrong_content_type() {
+        let request = request(&["Content-Type: text/plain"], Some(r#"{"x": 1, "y": -2}"#));
+        let e = request
+            .json::<Point>()
+            .expect_err("expected error with wrong content type");
+
+        assert!(matches!(e, JsonError::UnexpectedContentType(Some(_))));
+    }
+
+    #[test]
+    fn json_errors_when_missing_body() {
+        let request = request(&["Content-Type: application/json"], None);
+        let e = request
+            .json::<Point>()
+            .expect_err("expected error with missing body");
+
+        assert!(matches!(e, JsonError::MissingBody));
+    }
+
+    #[test]
+    fn json_errors_when_invalid_body() {
+        let request = request(&["Content-Type: application/json"], Some(r#"{"x": 1}"#));
+        let e = request
+            .json::<Point>()
+            .expect_err("expected error with invalid body");
+
+        assert!(matches!(e, JsonError::Deserialize(_)));
+    }
+
+    #[test]
+    fn response_json_serializes_value() {
+        let response = Response::json(StatusCode::Ok, &Point { x: 1, y: -2 }).unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.header("Content-Type"), Some("application/json"));
+        assert_eq!(response.body(), br#"{"x":1,"y":-2}"#);
+
+        let mut out = Vec::new();
+        response.write_to(&mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("\r\nContent-Length: 14\r\n"));
+    }
+
+    #[test]
+    fn response_json_errors_when_value_not_serializable() {
+        let map = BTreeMap::from([(vec![1u8], 1)]);
+        let e = Response::json(StatusCode::Ok, &map).expect_err("expected serialization error");
+
+        assert!(matches!(e, JsonError::Serialize(_)));
+    }
+
+    #[test]
+    fn json_deserializes_parsed_request() {
+        let message = "POST /points HTTP/1.1\r
+Host: 127.0.0.1:8080\r
+Content-Type: application/json\r
+Content-Length: 16\r
+\r
+{\"x\": 3, \"y\": 4}";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        let request = Request::try_from_reader(&mut reader).expect("error parsing request");
+        let point: Point = request.json().expect("error deserializing body");
+
+        assert_eq!(point, Point { x: 3, y: 4 });
+    }
+}
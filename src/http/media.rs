@@ -0,0 +1,307 @@
+//! Media types: the `Content-Type` header (RFC 7231 §3.1.1.1), and `Accept` header parsing for
+//! content negotiation (RFC 7231 §5.3.2).
+
+use std::path::Path;
+
+use super::Request;
+
+/// Media types of common file extensions, for [`guess_from_path`].
+const EXTENSION_TYPES: [(&str, &str); 26] = [
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("mjs", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("xml", "application/xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("wasm", "application/wasm"),
+    ("mp4", "video/mp4"),
+    ("mp3", "audio/mpeg"),
+];
+
+/// Guesses the `Content-Type` of a file from its extension, ignoring case. Unknown extensions
+/// and files without one give `application/octet-stream`.
+pub fn guess_from_path(path: impl AsRef<Path>) -> &'static str {
+    path.as_ref()
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| {
+            EXTENSION_TYPES
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+        })
+        .map_or("application/octet-stream", |(_, media_type)| media_type)
+}
+
+/// A media range from an `Accept` header, such as `text/html`, `text/*` or `*/*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    media_type: String,
+    subtype: String,
+    quality: f32,
+}
+
+impl MediaRange {
+    /// Main type, `*` for any.
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Subtype, `*` for any.
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Preference weight between 0 and 1, where 0 means "not acceptable".
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    /// Whether `media_type` (ie: `text/html`) falls within this range.
+    pub fn matches(&self, media_type: &str) -> bool {
+        let Some((main, sub)) = media_type.split_once('/') else {
+            return false;
+        };
+        (self.media_type == "*" || self.media_type.eq_ignore_ascii_case(main))
+            && (self.subtype == "*" || self.subtype.eq_ignore_ascii_case(sub))
+    }
+
+    /// Ranges naming a precise type are preferred over wildcards at equal quality.
+    fn specificity(&self) -> u8 {
+        match (self.media_type.as_str(), self.subtype.as_str()) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2,
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let mut params = value.split(';');
+        let (media_type, subtype) = params.next()?.trim().split_once('/')?;
+        if media_type.is_empty() || subtype.is_empty() || (media_type == "*" && subtype != "*") {
+            return None;
+        }
+
+        let mut quality = 1.0;
+        for param in params {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            if name.trim().eq_ignore_ascii_case("q") {
+                quality = value.trim().parse().ok()?;
+                if !(0.0..=1.0).contains(&quality) {
+                    return None;
+                }
+            }
+        }
+
+        Some(Self {
+            media_type: media_type.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            quality,
+        })
+    }
+}
+
+/// A parsed `Content-Type`, such as `text/html; charset=utf-8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    media_type: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// Parses a `type/subtype` followed by `;`-separated parameters. The media type and parameter
+    /// names are lowercased, parameter values are kept as sent with surrounding quotes removed.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let media_type = parts.next()?.trim();
+        let (main, sub) = media_type.split_once('/')?;
+        if main.is_empty() || sub.is_empty() {
+            return None;
+        }
+
+        let params = parts
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((name.trim().to_ascii_lowercase(), value.to_owned()))
+            })
+            .collect();
+
+        Some(Self {
+            media_type: media_type.to_ascii_lowercase(),
+            params,
+        })
+    }
+
+    /// Type and subtype without parameters, ie: `text/html`.
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Value of the parameter `name` (case-insensitive).
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+}
+
+impl Request {
+    /// The parsed `Content-Type` header, `None` if it is missing or malformed.
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.header("Content-Type").and_then(ContentType::parse)
+    }
+
+    /// Media ranges from the `Accept` header, most preferred first. Malformed entries are
+    /// skipped, and a missing header gives an empty list (meaning anything is acceptable).
+    pub fn accept(&self) -> Vec<MediaRange> {
+        let mut ranges: Vec<_> = self
+            .headers()
+            .get_all("Accept")
+            .flat_map(|value| value.split(','))
+            .filter_map(MediaRange::parse)
+            .collect();
+        // Stable, so equally ranked entries keep the client's order
+        ranges.sort_by(|a, b| {
+            b.quality
+                .total_cmp(&a.quality)
+                .then(b.specificity().cmp(&a.specificity()))
+        });
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> Vec<(String, f32)> {
+        let message = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nAccept: {}\r\n\r\n",
+            value
+        );
+        Request::parse(message.as_bytes())
+            .expect("error parsing request")
+            .accept()
+            .iter()
+            .map(|r| (format!("{}/{}", r.media_type(), r.subtype()), r.quality()))
+            .collect()
+    }
+
+    #[test]
+    fn accept_ranks_by_quality_then_specificity() {
+        let ranges = accept("*/*;q=0.1, text/*, text/html;level=1, application/json;q=0.8");
+
+        assert_eq!(
+            ranges,
+            [
+                ("text/html".to_owned(), 1.0),
+                ("text/*".to_owned(), 1.0),
+                ("application/json".to_owned(), 0.8),
+                ("*/*".to_owned(), 0.1),
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_skips_malformed_entries() {
+        let ranges = accept("text, */html, image/png;q=2, image/gif;q=abc, text/plain");
+
+        assert_eq!(ranges, [("text/plain".to_owned(), 1.0)]);
+    }
+
+    #[test]
+    fn accept_is_empty_without_header() {
+        let request = Request::parse(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+
+        assert!(request.accept().is_empty());
+    }
+
+    #[test]
+    fn media_range_matches_wildcards() {
+        let range = MediaRange::parse("text/*").unwrap();
+
+        assert!(range.matches("text/html"));
+        assert!(range.matches("TEXT/plain"));
+        assert!(!range.matches("image/png"));
+        assert!(MediaRange::parse("*/*").unwrap().matches("image/png"));
+    }
+
+    #[test]
+    fn content_type_splits_type_and_params() {
+        let content_type =
+            ContentType::parse("Text/HTML; charset=UTF-8; boundary=\"a b\"").unwrap();
+
+        assert_eq!(content_type.media_type(), "text/html");
+        assert_eq!(content_type.charset(), Some("UTF-8"));
+        assert_eq!(content_type.param("Boundary"), Some("a b"));
+        assert_eq!(content_type.params().count(), 2);
+    }
+
+    #[test]
+    fn content_type_parses_bare_type() {
+        let content_type = ContentType::parse("application/json").unwrap();
+
+        assert_eq!(content_type.media_type(), "application/json");
+        assert_eq!(content_type.charset(), None);
+    }
+
+    #[test]
+    fn content_type_is_none_when_missing_or_malformed() {
+        let request = Request::parse(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+
+        assert_eq!(request.content_type(), None);
+        assert_eq!(ContentType::parse("json"), None);
+    }
+
+    #[test]
+    fn guess_from_path_uses_extension() {
+        assert_eq!(guess_from_path("index.html"), "text/html; charset=utf-8");
+        assert_eq!(
+            guess_from_path("static/app.JS"),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(guess_from_path("/img/logo.png"), "image/png");
+        assert_eq!(guess_from_path("data.json"), "application/json");
+    }
+
+    #[test]
+    fn guess_from_path_falls_back_to_octet_stream() {
+        assert_eq!(
+            guess_from_path("archive.unknownext"),
+            "application/octet-stream"
+        );
+        assert_eq!(guess_from_path("Makefile"), "application/octet-stream");
+    }
+}
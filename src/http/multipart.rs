@@ -0,0 +1,234 @@
+//! `multipart/form-data` request bodies (RFC 7578), as sent by HTML forms uploading files.
+
+use std::{error::Error, fmt::Display};
+
+use super::{media::ContentType, Headers, Request};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MultipartError {
+    /// The request's content type is not `multipart/form-data`.
+    NotMultipart,
+    /// The `Content-Type` has no `boundary` parameter.
+    MissingBoundary,
+    MissingBody,
+    /// The body does not follow the multipart framing.
+    Malformed,
+}
+
+impl Display for MultipartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotMultipart => write!(f, "request is not multipart/form-data"),
+            Self::MissingBoundary => write!(f, "multipart content type has no boundary"),
+            Self::MissingBody => write!(f, "request has no body"),
+            Self::Malformed => write!(f, "malformed multipart body"),
+        }
+    }
+}
+
+impl Error for MultipartError {}
+
+/// One part of a multipart body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+impl Part {
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Form field name, from the `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.disposition_param("name")
+    }
+
+    /// Name of the uploaded file, from the `Content-Disposition` header.
+    pub fn filename(&self) -> Option<&str> {
+        self.disposition_param("filename")
+    }
+
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.headers
+            .get("Content-Type")
+            .and_then(ContentType::parse)
+    }
+
+    fn disposition_param(&self, name: &str) -> Option<&str> {
+        let disposition = self.headers.get("Content-Disposition")?;
+        disposition.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            if !key.trim().eq_ignore_ascii_case(name) {
+                return None;
+            }
+            let value = value.trim();
+            Some(
+                value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value),
+            )
+        })
+    }
+}
+
+impl Request {
+    /// Splits a `multipart/form-data` body into its parts, using the boundary from the
+    /// `Content-Type` header.
+    pub fn multipart(&self) -> Result<Vec<Part>, MultipartError> {
+        let content_type = self
+            .content_type()
+            .filter(|t| t.media_type() == "multipart/form-data")
+            .ok_or(MultipartError::NotMultipart)?;
+        let boundary = content_type
+            .param("boundary")
+            .filter(|b| !b.is_empty())
+            .ok_or(MultipartError::MissingBoundary)?;
+        let body = self.body_bytes().ok_or(MultipartError::MissingBody)?;
+
+        parse(body, boundary)
+    }
+}
+
+/// Parses a multipart `body` delimited by `boundary`, ignoring any preamble before the first
+/// delimiter and epilogue after the closing one.
+pub fn parse(body: &[u8], boundary: &str) -> Result<Vec<Part>, MultipartError> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+    // Every delimiter but a leading one is preceded by a CRLF, which belongs to it
+    let inner_delimiter = [b"\r\n", delimiter].concat();
+
+    let mut pos = if body.starts_with(delimiter) {
+        delimiter.len()
+    } else {
+        find(body, &inner_delimiter, 0).ok_or(MultipartError::Malformed)? + inner_delimiter.len()
+    };
+
+    let mut parts = Vec::new();
+    loop {
+        let rest = &body[pos..];
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+
+        // Transport padding is allowed between the delimiter and its CRLF
+        let line_end = find(rest, b"\r\n", 0).ok_or(MultipartError::Malformed)?;
+        if !rest[..line_end].iter().all(|b| *b == b' ' || *b == b'\t') {
+            return Err(MultipartError::Malformed);
+        }
+        pos += line_end + 2;
+
+        let (headers, body_start) = if body[pos..].starts_with(b"\r\n") {
+            (Headers::new(), pos + 2)
+        } else {
+            let head_end = find(body, b"\r\n\r\n", pos).ok_or(MultipartError::Malformed)?;
+            let head =
+                std::str::from_utf8(&body[pos..head_end]).map_err(|_| MultipartError::Malformed)?;
+            let lines: Vec<&str> = head.split("\r\n").collect();
+            let headers = Headers::from_lines(&lines).map_err(|_| MultipartError::Malformed)?;
+            (headers, head_end + 4)
+        };
+
+        let body_end = find(body, &inner_delimiter, body_start).ok_or(MultipartError::Malformed)?;
+        parts.push(Part {
+            headers,
+            body: body[body_start..body_end].to_vec(),
+        });
+        pos = body_end + inner_delimiter.len();
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "--xyz\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Holiday\r\n\
+        --xyz\r\n\
+        Content-Disposition: form-data; name=\"photo\"; filename=\"beach.png\"\r\n\
+        Content-Type: image/png\r\n\
+        \r\n\
+        PNG\r\n--not-the-boundary\r\n\
+        --xyz--\r\n";
+
+    fn request(content_type: &str, body: &str) -> Request {
+        let message = format!(
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        );
+        Request::parse(message.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn multipart_splits_parts() {
+        let parts = request("multipart/form-data; boundary=xyz", BODY)
+            .multipart()
+            .unwrap();
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name(), Some("title"));
+        assert_eq!(parts[0].filename(), None);
+        assert_eq!(parts[0].body(), b"Holiday");
+        assert_eq!(parts[1].name(), Some("photo"));
+        assert_eq!(parts[1].filename(), Some("beach.png"));
+        assert_eq!(parts[1].content_type().unwrap().media_type(), "image/png");
+        assert_eq!(parts[1].body(), b"PNG\r\n--not-the-boundary");
+    }
+
+    #[test]
+    fn multipart_ignores_preamble_and_epilogue() {
+        let body = "preamble\r\n--xyz\r\n\r\nvalue\r\n--xyz--\r\nepilogue";
+        let parts = request("multipart/form-data; boundary=\"xyz\"", body)
+            .multipart()
+            .unwrap();
+
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].headers().is_empty());
+        assert_eq!(parts[0].body(), b"value");
+    }
+
+    #[test]
+    fn multipart_rejects_other_content_types() {
+        let e = request("text/plain", "hello").multipart().unwrap_err();
+
+        assert_eq!(e, MultipartError::NotMultipart);
+    }
+
+    #[test]
+    fn multipart_requires_boundary() {
+        let e = request("multipart/form-data", BODY)
+            .multipart()
+            .unwrap_err();
+
+        assert_eq!(e, MultipartError::MissingBoundary);
+    }
+
+    #[test]
+    fn multipart_rejects_unterminated_body() {
+        let body = "--xyz\r\n\r\nvalue";
+        let e = request("multipart/form-data; boundary=xyz", body)
+            .multipart()
+            .unwrap_err();
+
+        assert_eq!(e, MultipartError::Malformed);
+    }
+}
@@ -0,0 +1,131 @@
+//! PROXY protocol v1, the line a TCP load balancer sends ahead of the proxied connection to tell
+//! the server the real client address.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>. Only enable it for
+//! connections coming from a trusted balancer, since anyone else could send a spoofed address.
+
+use std::{
+    io::Read,
+    net::{IpAddr, SocketAddr},
+};
+
+use super::{reader::RequestReader, RequestParsingError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyHeader {
+    /// A proxied TCP connection, from the client's `source` to the balancer's `destination`.
+    Tcp {
+        source: SocketAddr,
+        destination: SocketAddr,
+    },
+    /// The balancer doesn't know the addresses, such as for its own health checks.
+    Unknown,
+}
+
+impl ProxyHeader {
+    /// Reads and parses the PROXY line that must start the connection.
+    pub fn read_from<R: Read>(reader: &mut RequestReader<R>) -> Result<Self, RequestParsingError> {
+        Self::parse(&reader.read_line()?)
+    }
+
+    /// Parses a PROXY line without its terminating CRLF, such as
+    /// `PROXY TCP4 192.168.0.1 192.168.0.11 56324 443`.
+    pub fn parse(line: &str) -> Result<Self, RequestParsingError> {
+        let invalid = || RequestParsingError::InvalidProxyHeader;
+        let mut items = line.split(' ');
+        if items.next() != Some("PROXY") {
+            return Err(invalid());
+        }
+
+        let family = items.next().ok_or_else(invalid)?;
+        if family == "UNKNOWN" {
+            // Anything may follow, and must be ignored
+            return Ok(Self::Unknown);
+        }
+
+        let mut next = || items.next().ok_or_else(invalid);
+        let (source_ip, destination_ip) = (next()?, next()?);
+        let (source_port, destination_port) = (next()?, next()?);
+        if items.next().is_some() {
+            return Err(invalid());
+        }
+
+        let ip = |s: &str| -> Result<IpAddr, RequestParsingError> {
+            let ip: IpAddr = s.parse().map_err(|_| invalid())?;
+            match (family, ip) {
+                ("TCP4", IpAddr::V4(_)) | ("TCP6", IpAddr::V6(_)) => Ok(ip),
+                _ => Err(invalid()),
+            }
+        };
+        let port = |s: &str| -> Result<u16, RequestParsingError> {
+            // Ports are written without leading zeros
+            if s.len() > 1 && s.starts_with('0') {
+                return Err(invalid());
+            }
+            s.parse().map_err(|_| invalid())
+        };
+
+        Ok(Self::Tcp {
+            source: (ip(source_ip)?, port(source_port)?).into(),
+            destination: (ip(destination_ip)?, port(destination_port)?).into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_parses_tcp4() {
+        let header = ProxyHeader::parse("PROXY TCP4 192.168.0.1 192.168.0.11 56324 443").unwrap();
+
+        assert_eq!(
+            header,
+            ProxyHeader::Tcp {
+                source: "192.168.0.1:56324".parse().unwrap(),
+                destination: "192.168.0.11:443".parse().unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn proxy_parses_tcp6_and_unknown() {
+        let header = ProxyHeader::parse("PROXY TCP6 ::1 2001:db8::1 1234 80").unwrap();
+        assert!(
+            matches!(header, ProxyHeader::Tcp { source, .. } if source == "[::1]:1234".parse().unwrap())
+        );
+
+        let header = ProxyHeader::parse("PROXY UNKNOWN ffff::1 ::1 1 2").unwrap();
+        assert_eq!(header, ProxyHeader::Unknown);
+    }
+
+    #[test]
+    fn proxy_errors_when_malformed() {
+        for line in [
+            "GET / HTTP/1.1",
+            "PROXY TCP4 192.168.0.1 192.168.0.11 56324",
+            "PROXY TCP4 ::1 ::1 1 2",
+            "PROXY TCP5 1.1.1.1 1.1.1.1 1 2",
+            "PROXY TCP4 1.1.1.1 1.1.1.1 01 2",
+            "PROXY TCP4 1.1.1.1 1.1.1.1 1 70000",
+            "PROXY TCP4 1.1.1.1 1.1.1.1 1 2 3",
+        ] {
+            let e = ProxyHeader::parse(line).expect_err("expected error");
+            assert!(
+                matches!(e, RequestParsingError::InvalidProxyHeader),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn proxy_leaves_request_in_reader() {
+        let message = "PROXY TCP4 10.0.0.1 10.0.0.2 1000 80\r\nGET / HTTP/1.1\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        ProxyHeader::read_from(&mut reader).unwrap();
+
+        assert_eq!(reader.read_line().unwrap(), "GET / HTTP/1.1");
+    }
+}
@@ -0,0 +1,316 @@
+//! `Range` request header parsing (RFC 7233), for serving partial content.
+
+use std::{error::Error, fmt::Display, time::SystemTime};
+
+use super::{conditional::truncate_to_secs, date, Method, Request, Response, StatusCode};
+
+/// A requested byte range as written by the client: `(Some(start), end)` for `start-end` or
+/// `start-`, and `(None, Some(n))` for the last `n` bytes (`-n`).
+pub type ByteRange = (Option<u64>, Option<u64>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// The header isn't a valid `bytes` range set. Servers should ignore it and send the full
+    /// content.
+    Malformed,
+    /// None of the ranges overlap the content.
+    Unsatisfiable,
+}
+
+impl Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed Range header"),
+            Self::Unsatisfiable => write!(f, "no satisfiable range"),
+        }
+    }
+}
+
+impl Error for RangeError {}
+
+impl RangeError {
+    /// Status to answer with, `None` if the header should be ignored and the full content sent.
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Self::Malformed => None,
+            Self::Unsatisfiable => Some(StatusCode::RangeNotSatisfiable),
+        }
+    }
+}
+
+/// Validator sent in `If-Range`: the `Range` header only applies if the resource still matches
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfRange<'a> {
+    /// An entity tag with its quotes, and `W/` prefix if weak.
+    ETag(&'a str),
+    Date(SystemTime),
+}
+
+impl IfRange<'_> {
+    /// Whether a resource with the given entity tag and modification time matches. Entity tags
+    /// use the strong comparison, so weak tags never match, and dates must be exact.
+    pub fn matches(&self, etag: Option<&str>, last_modified: Option<SystemTime>) -> bool {
+        match *self {
+            Self::ETag(tag) => {
+                !tag.starts_with("W/")
+                    && etag.is_some_and(|etag| !etag.starts_with("W/") && etag == tag)
+            }
+            Self::Date(date) => {
+                last_modified.is_some_and(|modified| truncate_to_secs(modified) == date)
+            }
+        }
+    }
+}
+
+impl Request {
+    /// Ranges requested in the `Range` header, `None` if the header is missing.
+    pub fn range(&self) -> Option<Result<Vec<ByteRange>, RangeError>> {
+        self.header("Range").map(parse_range)
+    }
+
+    /// Validator in the `If-Range` header, `None` if the header is missing or is neither an
+    /// entity tag nor a date.
+    pub fn if_range(&self) -> Option<IfRange<'_>> {
+        let value = self.header("If-Range")?.trim();
+        if value.starts_with('"') || value.starts_with("W/\"") {
+            Some(IfRange::ETag(value))
+        } else {
+            date::parse(value).map(IfRange::Date)
+        }
+    }
+}
+
+impl Response {
+    /// Answers a `GET` for `content` with the part the request's `Range` header asks for, as a
+    /// `206 Partial Content`, or all of it as a `200 OK`.
+    ///
+    /// The range is ignored, and the full content sent, when it is malformed, when it lists
+    /// several ranges, or when an `If-Range` validator doesn't match the given entity tag and
+    /// modification time. Unsatisfiable ranges are answered with `416 Range Not Satisfiable`.
+    pub fn ranged(
+        request: &Request,
+        content: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<SystemTime>,
+    ) -> Self {
+        let length = content.len() as u64;
+        let current = request
+            .if_range()
+            .is_none_or(|validator| validator.matches(etag, last_modified));
+        let ranges = match request.range() {
+            Some(Ok(ranges)) if current && request.method() == Method::Get => Some(ranges),
+            _ => None,
+        };
+
+        let mut response = match ranges.map(|ranges| resolve(&ranges, length)) {
+            Some(Ok(resolved)) if resolved.len() == 1 => {
+                let (start, end) = resolved[0];
+                Self::new(StatusCode::PartialContent)
+                    .with_header("Content-Range", content_range(start, end, length))
+                    .with_body(content[start as usize..=end as usize].to_vec())
+            }
+            Some(Err(_)) => {
+                return Self::new(StatusCode::RangeNotSatisfiable)
+                    .with_header("Content-Range", format!("bytes */{}", length));
+            }
+            _ => Self::new(StatusCode::Ok).with_body(content.to_vec()),
+        };
+
+        response.set_header("Accept-Ranges", "bytes");
+        if let Some(etag) = etag {
+            response.set_header("ETag", etag);
+        }
+        if let Some(modified) = last_modified {
+            response.set_header("Last-Modified", date::format(modified));
+        }
+        response
+    }
+}
+
+fn parse_range(value: &str) -> Result<Vec<ByteRange>, RangeError> {
+    let (unit, set) = value.split_once('=').ok_or(RangeError::Malformed)?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Err(RangeError::Malformed);
+    }
+
+    let parse_pos = |s: &str| -> Result<Option<u64>, RangeError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(None);
+        }
+        s.parse().map(Some).map_err(|_| RangeError::Malformed)
+    };
+
+    set.split(',')
+        .map(|spec| {
+            let (start, end) = spec.split_once('-').ok_or(RangeError::Malformed)?;
+            match (parse_pos(start)?, parse_pos(end)?) {
+                (None, None) => Err(RangeError::Malformed),
+                (Some(start), Some(end)) if start > end => Err(RangeError::Malformed),
+                range => Ok(range),
+            }
+        })
+        .collect()
+}
+
+/// Resolves requested ranges against content of `length` bytes into inclusive `(start, end)`
+/// offsets, clamping ends past the content and dropping ranges that start after it.
+pub fn resolve(ranges: &[ByteRange], length: u64) -> Result<Vec<(u64, u64)>, RangeError> {
+    let resolved: Vec<_> = ranges
+        .iter()
+        .filter_map(|&range| match range {
+            (Some(start), end) if start < length => {
+                Some((start, end.map_or(length - 1, |e| e.min(length - 1))))
+            }
+            (None, Some(suffix)) if suffix > 0 && length > 0 => {
+                Some((length.saturating_sub(suffix), length - 1))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if resolved.is_empty() {
+        return Err(RangeError::Unsatisfiable);
+    }
+    Ok(resolved)
+}
+
+/// Value of the `Content-Range` header for a resolved range.
+pub fn content_range(start: u64, end: u64, length: u64) -> String {
+    format!("bytes {}-{}/{}", start, end, length)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn range_parses_single_range() {
+        assert_eq!(parse_range("bytes=0-499"), Ok(vec![(Some(0), Some(499))]));
+    }
+
+    #[test]
+    fn range_parses_multiple_and_open_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-499, 500-"),
+            Ok(vec![(Some(0), Some(499)), (Some(500), None)])
+        );
+    }
+
+    #[test]
+    fn range_parses_suffix_range() {
+        assert_eq!(parse_range("bytes=-500"), Ok(vec![(None, Some(500))]));
+    }
+
+    #[test]
+    fn range_errors_when_malformed() {
+        for value in [
+            "bytes=",
+            "bytes=-",
+            "bytes=5-1",
+            "bytes=a-b",
+            "items=0-1",
+            "0-1",
+        ] {
+            assert_eq!(parse_range(value), Err(RangeError::Malformed), "{}", value);
+        }
+    }
+
+    #[test]
+    fn resolve_clamps_and_drops_ranges() {
+        let ranges = parse_range("bytes=0-99,-300,900-,2000-").unwrap();
+
+        assert_eq!(
+            resolve(&ranges, 1000),
+            Ok(vec![(0, 99), (700, 999), (900, 999)])
+        );
+        assert_eq!(resolve(&[(Some(10), Some(5000))], 100), Ok(vec![(10, 99)]));
+    }
+
+    #[test]
+    fn resolve_errors_when_unsatisfiable() {
+        let ranges = parse_range("bytes=1000-,-0").unwrap();
+
+        assert_eq!(resolve(&ranges, 1000), Err(RangeError::Unsatisfiable));
+        assert_eq!(
+            RangeError::Unsatisfiable.status_code(),
+            Some(StatusCode::RangeNotSatisfiable)
+        );
+    }
+
+    #[test]
+    fn content_range_formats_header() {
+        assert_eq!(content_range(0, 499, 1000), "bytes 0-499/1000");
+    }
+
+    fn ranged_request(headers: &str) -> Request {
+        let message = format!("GET /file HTTP/1.1\r\nHost: localhost\r\n{}\r\n", headers);
+        Request::parse(message.as_bytes()).expect("error parsing request")
+    }
+
+    #[test]
+    fn if_range_parses_etag_and_date() {
+        let request = ranged_request("If-Range: \"v1\"\r\n");
+        assert_eq!(request.if_range(), Some(IfRange::ETag("\"v1\"")));
+
+        let request = ranged_request("If-Range: Sun, 06 Nov 1994 08:49:37 GMT\r\n");
+        assert_eq!(
+            request.if_range(),
+            Some(IfRange::Date(UNIX_EPOCH + Duration::from_secs(784_111_777)))
+        );
+
+        assert_eq!(ranged_request("If-Range: soon\r\n").if_range(), None);
+    }
+
+    #[test]
+    fn ranged_sends_partial_content_when_if_range_matches() {
+        let request = ranged_request("Range: bytes=0-4\r\nIf-Range: \"v1\"\r\n");
+
+        let response = Response::ranged(&request, b"hello world", Some("\"v1\""), None);
+
+        assert_eq!(response.status(), StatusCode::PartialContent);
+        assert_eq!(response.header("Content-Range"), Some("bytes 0-4/11"));
+        assert_eq!(response.body(), b"hello");
+    }
+
+    #[test]
+    fn ranged_sends_full_content_when_if_range_differs() {
+        let modified = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        for if_range in ["\"v0\"", "W/\"v1\"", "Sun, 06 Nov 1994 08:49:36 GMT"] {
+            let request =
+                ranged_request(&format!("Range: bytes=0-4\r\nIf-Range: {}\r\n", if_range));
+
+            let response =
+                Response::ranged(&request, b"hello world", Some("\"v1\""), Some(modified));
+
+            assert_eq!(response.status(), StatusCode::Ok, "{}", if_range);
+            assert_eq!(response.header("Content-Range"), None);
+            assert_eq!(response.body(), b"hello world");
+        }
+    }
+
+    #[test]
+    fn ranged_honors_range_when_if_range_date_matches() {
+        let modified = UNIX_EPOCH + Duration::from_millis(784_111_777_250);
+        let request =
+            ranged_request("Range: bytes=-5\r\nIf-Range: Sun, 06 Nov 1994 08:49:37 GMT\r\n");
+
+        let response = Response::ranged(&request, b"hello world", None, Some(modified));
+
+        assert_eq!(response.status(), StatusCode::PartialContent);
+        assert_eq!(response.body(), b"world");
+    }
+
+    #[test]
+    fn ranged_rejects_unsatisfiable_range() {
+        let request = ranged_request("Range: bytes=20-\r\n");
+
+        let response = Response::ranged(&request, b"hello world", None, None);
+
+        assert_eq!(response.status(), StatusCode::RangeNotSatisfiable);
+        assert_eq!(response.header("Content-Range"), Some("bytes */11"));
+    }
+}
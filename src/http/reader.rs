@@ -1,15 +1,33 @@
+//! Buffered reader splitting a byte stream on delimiters, as used to read HTTP request heads.
+
 use std::{
     collections::VecDeque,
     error::Error,
     fmt::Display,
-    io::{self, Read},
+    io::{self, BufRead, Read},
     string::FromUtf8Error,
+    sync::Arc,
+    time::Instant,
 };
 
+use crate::clock::{Clock, SystemClock};
+
 #[derive(Debug)]
-pub(crate) enum RequestReaderError {
+pub enum RequestReaderError {
     Io(io::Error),
     Encoding(FromUtf8Error),
+    /// The head has more header lines than [`Limits::max_headers`].
+    TooManyHeaders {
+        limit: usize,
+    },
+    /// The start line is longer than [`Limits::max_start_line`].
+    StartLineTooLong {
+        limit: usize,
+    },
+    /// A header line is longer than [`Limits::max_header_line`].
+    HeaderLineTooLong {
+        limit: usize,
+    },
 }
 
 impl Display for RequestReaderError {
@@ -17,6 +35,13 @@ impl Display for RequestReaderError {
         match self {
             Self::Io(e) => write!(f, "encountered an IO error reading the request: {}", e),
             Self::Encoding(e) => write!(f, "could not decode the bytes: {}", e),
+            Self::TooManyHeaders { limit } => write!(f, "more than {} headers", limit),
+            Self::StartLineTooLong { limit } => {
+                write!(f, "start line longer than {} bytes", limit)
+            }
+            Self::HeaderLineTooLong { limit } => {
+                write!(f, "header line longer than {} bytes", limit)
+            }
         }
     }
 }
@@ -37,65 +62,362 @@ impl From<FromUtf8Error> for RequestReaderError {
 
 const BUFFERED_READER_BUF_SIZE: usize = 2048;
 
-pub(crate) struct RequestReader<R: Read> {
+pub const DEFAULT_MAX_HEADERS: usize = 100;
+pub const DEFAULT_MAX_START_LINE: usize = 8 * 1024;
+pub const DEFAULT_MAX_HEADER_LINE: usize = 8 * 1024;
+
+/// Most empty lines skipped before a start line, as sent by clients terminating a previous body
+/// with an extra CRLF.
+const MAX_LEADING_EMPTY_LINES: usize = 8;
+
+/// Bounds on what a [`RequestReader`] accepts, protecting against clients sending oversized
+/// requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Most header lines accepted in a request head.
+    pub max_headers: usize,
+    /// Most bytes read ahead from the underlying reader and kept for later reads, such as the
+    /// start of a pipelined request. Reads never fetch more than this beyond what was asked
+    /// for, so the internal buffer can't grow past it.
+    pub max_buffered: usize,
+    /// Longest start line accepted, in bytes without its `\r\n`. Bounds the request target
+    /// independently of the headers.
+    pub max_start_line: usize,
+    /// Longest single header line accepted, in bytes without its `\r\n`. Bounds one huge header
+    /// independently of the number of headers.
+    pub max_header_line: usize,
+    /// Most bytes read from the underlying reader over its whole life, such as all the requests
+    /// of a connection, `None` for no limit. Reads past it fail.
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_buffered: BUFFERED_READER_BUF_SIZE,
+            max_start_line: DEFAULT_MAX_START_LINE,
+            max_header_line: DEFAULT_MAX_HEADER_LINE,
+            max_total_bytes: None,
+        }
+    }
+}
+
+/// Reads from `R` until a delimiter, keeping any bytes read past it for the next call. Implements
+/// [`Read`] itself, returning the buffered bytes before reading from `R` again.
+pub struct RequestReader<R: Read> {
     reader: R,
     internal: VecDeque<u8>,
+    deadline: Option<Instant>,
+    limits: Limits,
+    buf_read: Option<BufReadFns<R>>,
+    clock: Arc<dyn Clock>,
+    bytes_read: u64,
 }
 
+/// The [`BufRead`] methods of a reader given to [`RequestReader::from_buf_read`], kept as
+/// function pointers since `R` is only known to implement [`Read`] elsewhere.
+struct BufReadFns<R> {
+    fill_buf: fn(&mut R) -> io::Result<&[u8]>,
+    consume: fn(&mut R, usize),
+}
+
+impl<R> Clone for BufReadFns<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for BufReadFns<R> {}
+
 impl<R: Read> RequestReader<R> {
     pub fn from_reader(r: R) -> Self {
         Self {
             reader: r,
             internal: VecDeque::with_capacity(BUFFERED_READER_BUF_SIZE),
+            deadline: None,
+            limits: Limits::default(),
+            buf_read: None,
+            clock: Arc::new(SystemClock),
+            bytes_read: 0,
+        }
+    }
+
+    /// Reads from a reader that already buffers, such as a [`io::BufReader`]. Delimiters are
+    /// searched for in its buffer and only the bytes up to them are copied out, instead of
+    /// copying everything into an internal buffer first.
+    ///
+    /// Bytes past a delimiter stay in the reader's own buffer, so they aren't counted by
+    /// [`RequestReader::buffered_len`] or returned by [`RequestReader::peek`], and
+    /// [`Limits::max_buffered`] doesn't apply.
+    pub fn from_buf_read(r: R) -> Self
+    where
+        R: BufRead,
+    {
+        Self {
+            buf_read: Some(BufReadFns {
+                fill_buf: R::fill_buf,
+                consume: R::consume,
+            }),
+            ..Self::from_reader(r)
         }
     }
 
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Sets a point in time after which reads fail with [`io::ErrorKind::TimedOut`], bounding the
+    /// total time spent reading rather than each read. A read already blocked on the underlying
+    /// reader isn't interrupted, so sockets should also have a read timeout set.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Sets the clock the deadline is checked against, [`SystemClock`] by default.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Reads the start line like [`RequestReader::read_line`], erroring as soon as it grows
+    /// past [`Limits::max_start_line`] instead of buffering it whole.
+    ///
+    /// A few empty lines before it are skipped (RFC 7230 §3.5), and so is a UTF-8 byte order mark
+    /// prepended by some clients. Past that many empty lines, the empty line is returned.
     pub fn read_start_line(&mut self) -> Result<String, RequestReaderError> {
-        let bytes = self.read_until_with_chunk_size::<16>("\r\n")?;
-        String::from_utf8(bytes)
-            .map(|s| s.strip_suffix("\r\n").unwrap().to_owned())
-            .map_err(Into::into)
+        let limit = self.limits.max_start_line;
+        let mut line = String::new();
+        for _ in 0..=MAX_LEADING_EMPTY_LINES {
+            line = self
+                .read_line_limited(limit)?
+                .ok_or(RequestReaderError::StartLineTooLong { limit })?;
+            if !line.is_empty() {
+                break;
+            }
+        }
+
+        match line.strip_prefix('\u{feff}') {
+            Some(stripped) => Ok(stripped.to_owned()),
+            None => Ok(line),
+        }
     }
 
+    /// Reads a line like [`RequestReader::read_line`], or returns `None` as soon as it is known
+    /// to be longer than `limit` bytes.
+    pub(crate) fn read_line_limited(
+        &mut self,
+        limit: usize,
+    ) -> Result<Option<String>, RequestReaderError> {
+        let Some(mut bytes) = self.read_until_limited("\r\n", limit)? else {
+            return Ok(None);
+        };
+        bytes.truncate(bytes.len() - 2);
+        Ok(Some(String::from_utf8(bytes)?))
+    }
+
+    /// Total number of bytes read from the underlying reader, consumed or still buffered.
+    /// Bytes a [`BufRead`] reader buffered itself are only counted once consumed.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Number of bytes already read from the underlying reader but not consumed yet, such as the
+    /// start of a pipelined request. Never blocks.
+    pub fn buffered_len(&self) -> usize {
+        self.internal.len()
+    }
+
+    /// The bytes counted by [`RequestReader::buffered_len`], without consuming them.
+    pub fn peek(&mut self) -> &[u8] {
+        self.internal.make_contiguous()
+    }
+
+    /// Gives back the underlying reader and the bytes read from it but not consumed yet, such as
+    /// to take over the connection for another protocol after an upgrade. The buffered bytes come
+    /// first, they were sent before anything still unread.
+    pub fn into_inner(self) -> (R, VecDeque<u8>) {
+        (self.reader, self.internal)
+    }
+
+    /// Waits until at least one byte is available, without consuming it. Returns the number of
+    /// bytes now buffered, 0 if the reader reached its end.
+    pub fn fill_buf(&mut self) -> io::Result<usize> {
+        if let Some(fns) = self.buf_read.filter(|_| self.internal.is_empty()) {
+            return (fns.fill_buf)(&mut self.reader).map(<[u8]>::len);
+        }
+        if self.internal.is_empty() {
+            let mut tmp = [0; BUFFERED_READER_BUF_SIZE];
+            let block = self.limits.max_buffered.clamp(1, BUFFERED_READER_BUF_SIZE);
+            let n = self.read_counted(&mut tmp[..block])?;
+            self.internal.extend(&tmp[..n]);
+        }
+        Ok(self.internal.len())
+    }
+
+    /// Reads up to the next `\r\n`, returning the line without its terminator. Bytes already in
+    /// the internal buffer are consumed before reading from the underlying reader.
+    pub fn read_line(&mut self) -> Result<String, RequestReaderError> {
+        let mut bytes = self.read_until_unbounded("\r\n")?;
+        bytes.truncate(bytes.len() - 2);
+        String::from_utf8(bytes).map_err(Into::into)
+    }
+
+    /// Reads header lines up to the empty line ending the head. Lines are read one at a time, so
+    /// a head with too many headers, or a line past [`Limits::max_header_line`], errors as soon
+    /// as the limit is crossed.
     pub fn read_headers(&mut self) -> Result<Vec<String>, RequestReaderError> {
-        let bytes = self.read_until_with_chunk_size::<64>("\r\n\r\n")?;
-        let string =
-            String::from_utf8(bytes).map(|s| s.strip_suffix("\r\n\r\n").unwrap().to_owned())?;
-        Ok(string.split("\r\n").map(ToOwned::to_owned).collect())
+        let limit = self.limits.max_header_line;
+        let mut headers = Vec::new();
+        loop {
+            let line = self
+                .read_line_limited(limit)?
+                .ok_or(RequestReaderError::HeaderLineTooLong { limit })?;
+            if line.is_empty() {
+                return Ok(headers);
+            }
+            if headers.len() == self.limits.max_headers {
+                return Err(RequestReaderError::TooManyHeaders {
+                    limit: self.limits.max_headers,
+                });
+            }
+            headers.push(line);
+        }
     }
 
-    fn read_until_with_chunk_size<const N: usize>(
-        &mut self,
-        pattern: &str,
-    ) -> std::io::Result<Vec<u8>> {
+    /// Reads the whole request head (start line and headers) into a single buffer, including the
+    /// terminating empty line. Used to parse headers without allocating for each of them.
+    pub fn read_head_bytes(&mut self) -> Result<Vec<u8>, RequestReaderError> {
+        self.read_until_unbounded("\r\n\r\n").map_err(Into::into)
+    }
+
+    /// Reads up to and including the next occurrence of `pattern`. Errors with
+    /// [`io::ErrorKind::UnexpectedEof`] if the reader ends before the pattern is found.
+    pub fn read_until(&mut self, pattern: &str) -> io::Result<Vec<u8>> {
+        self.read_until_unbounded(pattern)
+    }
+
+    fn read_until_unbounded(&mut self, pattern: &str) -> io::Result<Vec<u8>> {
+        // Nothing can exceed a `usize::MAX` limit
+        Ok(self
+            .read_until_limited(pattern, usize::MAX)?
+            .unwrap_or_default())
+    }
+
+    /// Reads up to and including `pattern`, or returns `None` once more than `limit` bytes were
+    /// read before it.
+    ///
+    /// The underlying reader is read in blocks into the internal buffer, which is then searched
+    /// in place. Only the bytes up to the end of the pattern are taken out of it, so the start of
+    /// a pipelined request read along with a head stays buffered, in order.
+    fn read_until_limited(&mut self, pattern: &str, limit: usize) -> io::Result<Option<Vec<u8>>> {
         let pattern_bytes = pattern.as_bytes();
-        let mut output = Vec::with_capacity(N);
-        let mut buf = [0; N];
+        if pattern_bytes.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+        if let Some(fns) = self.buf_read.filter(|_| self.internal.is_empty()) {
+            return self.read_until_in_buf_read(fns, pattern_bytes, limit);
+        }
+        let mut output = Vec::new();
 
         loop {
-            let n = self.read(&mut buf)?;
-            if n == 0 {
+            if self.fill_internal()? == 0 {
                 return Err(io::ErrorKind::UnexpectedEof.into());
             }
-            if let Some(index) = buf[..n]
+
+            // The pattern may straddle two blocks, so the search starts in the previous one
+            let previous = output.len();
+            let search_start = previous.saturating_sub(pattern_bytes.len() - 1);
+            output.extend(self.internal.make_contiguous().iter());
+            if let Some(index) = output[search_start..]
                 .windows(pattern_bytes.len())
                 .position(|w| w == pattern_bytes)
             {
-                // Pattern found
-                let end_idx = index + pattern_bytes.len();
-                output.extend(&buf[..end_idx]);
-                if end_idx < n {
-                    // Bytes after the pattern should be added to internal buffer for later
-                    // reading.
-                    self.internal.extend(&buf[end_idx..]);
+                let end_idx = search_start + index + pattern_bytes.len();
+                self.internal.drain(..end_idx - previous);
+                if end_idx - pattern_bytes.len() > limit {
+                    return Ok(None);
+                }
+                output.truncate(end_idx);
+                return Ok(Some(output));
+            }
+            self.internal.clear();
+            if output.len() > limit.saturating_add(pattern_bytes.len()) {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// [`RequestReader::read_until_limited`] for a [`BufRead`] reader, searching its buffer and
+    /// only consuming up to the end of the pattern.
+    fn read_until_in_buf_read(
+        &mut self,
+        fns: BufReadFns<R>,
+        pattern: &[u8],
+        limit: usize,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let mut output = Vec::new();
+
+        loop {
+            if self.deadline_passed() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let remaining = self.remaining_bytes()?;
+            let available = (fns.fill_buf)(&mut self.reader)?;
+            let available = &available[..available.len().min(remaining)];
+            if available.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let end = find_end(&output, available, pattern);
+            let taken = end.unwrap_or(available.len());
+            output.extend_from_slice(&available[..taken]);
+            (fns.consume)(&mut self.reader, taken);
+            self.bytes_read += taken as u64;
+
+            if end.is_some() {
+                if output.len() - pattern.len() > limit {
+                    return Ok(None);
                 }
-                return Ok(output);
+                return Ok(Some(output));
             }
+            if output.len() > limit.saturating_add(pattern.len()) {
+                return Ok(None);
+            }
+        }
+    }
 
-            // Pattern not found, add all bytes to output
-            output.extend(buf);
-            buf = [0; N];
+    /// Reads from the underlying reader, within what [`Limits::max_total_bytes`] has left.
+    fn read_counted(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = buf.len().min(self.remaining_bytes()?);
+        let n = self.reader.read(&mut buf[..max])?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+
+    /// Bytes that may still be read from the underlying reader, erroring if there are none left.
+    fn remaining_bytes(&self) -> io::Result<usize> {
+        match self.limits.max_total_bytes {
+            None => Ok(usize::MAX),
+            Some(max) if self.bytes_read >= max => Err(io::Error::other(format!(
+                "read limit of {} bytes for the connection reached",
+                max
+            ))),
+            Some(max) => Ok(usize::try_from(max - self.bytes_read).unwrap_or(usize::MAX)),
+        }
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| self.clock.now() >= deadline)
+    }
+
+    /// Reads a block from the underlying reader if the internal buffer is empty, like
+    /// [`RequestReader::fill_buf`] but failing once the deadline passed.
+    fn fill_internal(&mut self) -> io::Result<usize> {
+        if self.internal.is_empty() && self.deadline_passed() {
+            return Err(io::ErrorKind::TimedOut.into());
         }
+        self.fill_buf()
     }
 
     fn internal_read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
@@ -103,60 +425,138 @@ impl<R: Read> RequestReader<R> {
         if len == 0 {
             return Ok(0);
         }
-
-        // Read n bytes, where n is the minimum size between the read buffer and the internal
-        // buffer (ie: empty the internal buffer)
-        let n = len.min(self.internal.len());
-        for idx in buf.iter_mut().take(n) {
-            // NOTE: This can't be efficient, is there a better way to take n elements and save to
-            // a slice?
-            *idx = self
-                .internal
-                .pop_front()
-                .expect("Internal buffer empty when expecting data");
+        if self.deadline_passed() {
+            return Err(io::ErrorKind::TimedOut.into());
         }
 
-        if n < len {
-            // Still room in external buffer, read from reader
-            let external_free = len - n;
-            let mut tmp = [0; BUFFERED_READER_BUF_SIZE];
-            let tmp_size = self.reader.read(&mut tmp)?;
-            let end_idx = tmp_size.min(external_free);
-            buf[n..(n + end_idx)].clone_from_slice(&tmp[..end_idx]);
-            // Save remaining to internal buffer
-            if tmp_size > end_idx {
-                self.internal.extend(&tmp[end_idx..])
-            }
-            Ok(n + end_idx)
-        } else {
-            Ok(n)
+        if self.internal.is_empty() && self.buf_read.is_some() {
+            // The reader buffers itself
+            return self.read_counted(buf);
+        }
+        if !self.internal.is_empty() {
+            // Serve buffered bytes first. The underlying reader isn't touched, since it could
+            // block while the client waits for a response.
+            let n = len.min(self.internal.len());
+            for (dst, src) in buf.iter_mut().zip(self.internal.drain(..n)) {
+                *dst = src;
+            }
+            return Ok(n);
         }
+
+        // Internal buffer empty, read a whole block from the reader and keep what doesn't fit,
+        // up to the buffering limit
+        let mut tmp = [0; BUFFERED_READER_BUF_SIZE];
+        let block = len
+            .saturating_add(self.limits.max_buffered)
+            .min(BUFFERED_READER_BUF_SIZE);
+        let tmp_size = self.read_counted(&mut tmp[..block])?;
+        let n = tmp_size.min(len);
+        buf[..n].copy_from_slice(&tmp[..n]);
+        self.internal.extend(&tmp[n..tmp_size]);
+        Ok(n)
     }
 }
 
+/// Index in `next` just past the first occurrence of `pattern` in `previous` followed by `next`,
+/// if it ends within `next`. Matches ending within `previous` are assumed to have been found
+/// already.
+fn find_end(previous: &[u8], next: &[u8], pattern: &[u8]) -> Option<usize> {
+    // Only the end of `previous` can start a match straddling both
+    let carried = &previous[previous.len().saturating_sub(pattern.len() - 1)..];
+    let straddling = (0..carried.len()).find_map(|start| {
+        let (head, tail) = pattern.split_at(carried.len() - start);
+        (carried[start..] == *head && next.starts_with(tail)).then_some(tail.len())
+    });
+    straddling.or_else(|| {
+        next.windows(pattern.len())
+            .position(|w| w == pattern)
+            .map(|index| index + pattern.len())
+    })
+}
+
 impl<R: Read> Read for RequestReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.internal_read(buf)
     }
 }
 
+/// Readers delivering their input in awkward pieces, so tests can reproduce delimiters, chunks
+/// and timeouts straddling reads.
+#[cfg(test)]
+pub(crate) mod slow {
+    use std::io::{self, Read};
+
+    /// Yields at most `max` bytes per read, optionally failing every other read with
+    /// [`io::ErrorKind::WouldBlock`] like a non-blocking socket with nothing available yet.
+    pub(crate) struct SlowReader {
+        data: Vec<u8>,
+        max: usize,
+        would_block: bool,
+        blocked: bool,
+        reads: usize,
+    }
+
+    impl SlowReader {
+        pub(crate) fn new(data: impl Into<Vec<u8>>, max: usize) -> Self {
+            assert!(max > 0, "a reader yielding nothing looks finished");
+            Self {
+                data: data.into(),
+                max,
+                would_block: false,
+                blocked: false,
+                reads: 0,
+            }
+        }
+
+        /// Fails each read that would return data with `WouldBlock` first.
+        pub(crate) fn with_would_block(mut self) -> Self {
+            self.would_block = true;
+            self
+        }
+
+        /// Calls to `read`, `WouldBlock` failures included.
+        pub(crate) fn reads(&self) -> usize {
+            self.reads
+        }
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            if self.would_block && !self.blocked && !self.data.is_empty() && !buf.is_empty() {
+                self.blocked = true;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.blocked = false;
+
+            let n = self.max.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data.drain(..n);
+            Ok(n)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::time::Duration;
+
+    use super::{slow::SlowReader, *};
+    use crate::clock::ManualClock;
 
     #[test]
     fn read_until_returns_correct_output() {
         let data = "ABCDEF012345\r\nXX".as_bytes();
         let mut req_reader = RequestReader::from_reader(data);
         let out = req_reader
-            .read_until_with_chunk_size::<16>("\r\n")
+            .read_until_unbounded("\r\n")
             .expect("error reading until");
 
         // Expect correct output
         assert_eq!(out, "ABCDEF012345\r\n".as_bytes());
 
-        // 14 first characters read into output, but since chunk is 16 bytes long, expect the
-        // remaining 2 bytes to have been read and stored into the internal buffer
+        // The whole input is read as one block, expect the 2 bytes after the pattern to be kept
+        // in the internal buffer
         assert_eq!(req_reader.internal, "XX".as_bytes())
     }
 
@@ -165,9 +565,485 @@ mod tests {
         let data = "ABCD".as_bytes();
         let mut req_reader = RequestReader::from_reader(data);
         let e = req_reader
-            .read_until_with_chunk_size::<16>("badpattern")
+            .read_until_unbounded("badpattern")
             .expect_err("expected error when reading until non-existent pattern");
 
         assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
     }
+
+    #[test]
+    fn read_until_finds_pattern_across_chunks() {
+        let data = "ABCDEFGHIJKLMNO\r\nXX".as_bytes();
+        let mut req_reader = RequestReader::from_reader(data);
+        let out = req_reader
+            .read_until_unbounded("\r\n")
+            .expect("error reading until");
+
+        assert_eq!(out, "ABCDEFGHIJKLMNO\r\n".as_bytes());
+        assert_eq!(req_reader.internal, "XX".as_bytes())
+    }
+
+    #[test]
+    fn read_until_keeps_buffered_bytes_in_order() {
+        let data = "A\r\nB\r\nC\r\n".as_bytes();
+        let mut req_reader = RequestReader::from_reader(data);
+        req_reader
+            .read_until_unbounded("\r\n")
+            .expect("error reading until");
+        let out = req_reader
+            .read_until_unbounded("\r\n")
+            .expect("error reading until");
+
+        assert_eq!(out, "B\r\n".as_bytes());
+        assert_eq!(req_reader.internal, "C\r\n".as_bytes())
+    }
+
+    #[test]
+    fn read_serves_internal_buffer_without_reading_further() {
+        // Reading past the first line would panic, as if blocking on a socket
+        struct Once(Option<&'static [u8]>);
+        impl Read for Once {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let data = self
+                    .0
+                    .take()
+                    .expect("reader called after data was buffered");
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+        }
+
+        let mut req_reader = RequestReader::from_reader(Once(Some(b"AB\r\nCD")));
+        req_reader
+            .read_until_unbounded("\r\n")
+            .expect("error reading until");
+        let mut buf = [0; 16];
+        let n = req_reader
+            .read(&mut buf)
+            .expect("error reading buffered bytes");
+
+        assert_eq!(&buf[..n], b"CD");
+    }
+
+    #[test]
+    fn read_line_strips_terminator() {
+        let data = "first line\r\n\r\nthird\r\n".as_bytes();
+        let mut req_reader = RequestReader::from_reader(data);
+
+        assert_eq!(req_reader.read_line().unwrap(), "first line");
+        assert_eq!(req_reader.read_line().unwrap(), "");
+        assert_eq!(req_reader.read_line().unwrap(), "third");
+        let e = req_reader
+            .read_line()
+            .expect_err("expected error when no line is left");
+        assert!(matches!(e, RequestReaderError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn read_line_uses_internal_buffer_first() {
+        let data = "buffered\r\nunread\r\n".as_bytes();
+        let mut req_reader = RequestReader::from_reader("".as_bytes());
+        req_reader.internal.extend(data);
+
+        assert_eq!(req_reader.read_line().unwrap(), "buffered");
+        assert_eq!(req_reader.read_line().unwrap(), "unread");
+    }
+
+    #[test]
+    fn read_line_errors_when_invalid_utf8() {
+        let mut req_reader = RequestReader::from_reader(&b"\xff\xfe\r\n"[..]);
+        let e = req_reader
+            .read_line()
+            .expect_err("expected error when line isn't UTF-8");
+
+        assert!(matches!(e, RequestReaderError::Encoding(_)));
+    }
+
+    #[test]
+    fn read_until_returns_bytes_including_pattern() {
+        let mut reader = RequestReader::from_reader("key=value;rest".as_bytes());
+
+        assert_eq!(reader.read_until(";").unwrap(), b"key=value;");
+        assert!(reader.read_until(";").is_err());
+    }
+
+    #[test]
+    fn read_until_empty_pattern_reads_nothing() {
+        let mut reader = RequestReader::from_reader("abc".as_bytes());
+
+        assert_eq!(reader.read_until("").unwrap(), b"");
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "abc");
+    }
+
+    /// Yields one byte per read, forever, like a client trickling data.
+    struct Trickle;
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            buf[0] = b'a';
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn reader_errors_after_deadline() {
+        let clock = Arc::new(ManualClock::new());
+        let mut reader = RequestReader::from_reader(Trickle);
+        reader.set_clock(clock.clone());
+        reader.set_deadline(Some(clock.now() + Duration::from_secs(30)));
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+
+        clock.advance(Duration::from_secs(30));
+
+        let e = reader.read_line().expect_err("expected timeout");
+        assert!(matches!(e, RequestReaderError::Io(e) if e.kind() == io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn reader_reads_before_deadline() {
+        let clock = Arc::new(ManualClock::new());
+        let mut reader = RequestReader::from_reader("line\r\nnext\r\n".as_bytes());
+        reader.set_clock(clock.clone());
+        reader.set_deadline(Some(clock.now() + Duration::from_secs(30)));
+
+        clock.advance(Duration::from_secs(29));
+
+        assert_eq!(reader.read_line().unwrap(), "line");
+    }
+
+    #[test]
+    fn read_headers_errors_before_reading_past_limit() {
+        let message = "A: 1\r\nB: 2\r\nC: 3\r\n\r\n";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        reader.set_limits(Limits {
+            max_headers: 2,
+            ..Default::default()
+        });
+
+        let e = reader.read_headers().expect_err("expected error");
+
+        assert!(matches!(e, RequestReaderError::TooManyHeaders { limit: 2 }));
+        // Only the lines up to the one crossing the limit were consumed
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "\r\n");
+    }
+
+    #[test]
+    fn fill_buf_keeps_bytes_for_next_read() {
+        let mut reader = RequestReader::from_reader("line\r\n".as_bytes());
+
+        assert_eq!(reader.fill_buf().unwrap(), 6);
+        assert_eq!(reader.read_line().unwrap(), "line");
+        assert_eq!(reader.fill_buf().unwrap(), 0);
+    }
+
+    #[test]
+    fn reader_buffers_at_most_max_buffered() {
+        let mut message = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec();
+        message.extend(vec![b'x'; 16 * 1024]);
+        let mut reader = RequestReader::from_reader(message.as_slice());
+        reader.set_limits(Limits {
+            max_buffered: 100,
+            ..Default::default()
+        });
+
+        reader.read_line().unwrap();
+        reader.read_headers().unwrap();
+        assert!(reader.internal.len() <= 100);
+
+        // Nothing is lost, the remainder is read from the underlying reader as needed
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest.len(), 16 * 1024);
+    }
+
+    #[test]
+    fn read_start_line_errors_at_limit() {
+        let message = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(64 * 1024));
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        reader.set_limits(Limits {
+            max_start_line: 1024,
+            ..Default::default()
+        });
+
+        let e = reader.read_start_line().expect_err("expected error");
+
+        assert!(matches!(
+            e,
+            RequestReaderError::StartLineTooLong { limit: 1024 }
+        ));
+        // The line was rejected without reading all of it
+        assert!(reader.read_to_end(&mut Vec::new()).unwrap() > 60 * 1024);
+    }
+
+    #[test]
+    fn read_start_line_skips_leading_empty_lines() {
+        let mut reader = RequestReader::from_reader("\r\nGET / HTTP/1.1\r\n".as_bytes());
+        assert_eq!(reader.read_start_line().unwrap(), "GET / HTTP/1.1");
+
+        let message = format!(
+            "{}GET / HTTP/1.1\r\n",
+            "\r\n".repeat(MAX_LEADING_EMPTY_LINES)
+        );
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        assert_eq!(reader.read_start_line().unwrap(), "GET / HTTP/1.1");
+
+        // Bounded, so a stream of empty lines can't keep the reader busy
+        let message = "\r\n".repeat(MAX_LEADING_EMPTY_LINES + 2);
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        assert_eq!(reader.read_start_line().unwrap(), "");
+    }
+
+    #[test]
+    fn bytes_read_counts_every_request() {
+        let message = b"GET /a HTTP/1.1\r\nHost: a\r\n\r\nGET /b HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut reader = RequestReader::from_reader(&message[..]);
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+        assert_eq!(reader.bytes_read(), message.len() as u64);
+
+        // Bytes still in a BufRead's own buffer aren't read yet
+        let mut reader = RequestReader::from_buf_read(io::BufReader::new(&message[..]));
+        reader.read_start_line().unwrap();
+        assert_eq!(reader.bytes_read(), "GET /a HTTP/1.1\r\n".len() as u64);
+    }
+
+    #[test]
+    fn reads_fail_past_total_byte_limit() {
+        let limits = Limits {
+            max_total_bytes: Some(40),
+            ..Default::default()
+        };
+        let message = b"GET /a HTTP/1.1\r\nHost: a\r\n\r\nGET /b HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let mut reader = RequestReader::from_reader(&message[..]);
+        reader.set_limits(limits);
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+        // The second request goes over the limit
+        assert!(matches!(
+            reader.read_start_line(),
+            Err(RequestReaderError::Io(e)) if e.kind() == io::ErrorKind::Other
+        ));
+        assert_eq!(reader.bytes_read(), 40);
+
+        let mut reader = RequestReader::from_buf_read(io::BufReader::new(&message[..]));
+        reader.set_limits(limits);
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+        assert!(reader.read_start_line().is_err());
+        assert_eq!(reader.bytes_read(), 40);
+    }
+
+    #[test]
+    fn read_start_line_strips_byte_order_mark() {
+        let mut reader = RequestReader::from_reader("\u{feff}GET / HTTP/1.1\r\n".as_bytes());
+
+        assert_eq!(reader.read_start_line().unwrap(), "GET / HTTP/1.1");
+    }
+
+    #[test]
+    fn read_start_line_accepts_line_at_limit() {
+        let mut reader = RequestReader::from_reader("GET / HTTP/1.1\r\n".as_bytes());
+        reader.set_limits(Limits {
+            max_start_line: 14,
+            ..Default::default()
+        });
+
+        assert_eq!(reader.read_start_line().unwrap(), "GET / HTTP/1.1");
+    }
+
+    #[test]
+    fn buffered_len_counts_bytes_past_request() {
+        let message = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\nGET /next";
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+
+        assert_eq!(reader.buffered_len(), 9);
+        assert_eq!(reader.peek(), b"GET /next");
+        // Peeking doesn't consume
+        assert_eq!(reader.read_until(" ").unwrap(), b"GET ");
+        assert_eq!(reader.buffered_len(), 5);
+    }
+
+    #[test]
+    fn read_head_bytes_leaves_pipelined_request_buffered() {
+        let message =
+            b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\nGET /b HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        for size in [1, 3, 7, 64, message.len()] {
+            let mut reader = RequestReader::from_reader(SlowReader::new(&message[..], size));
+
+            let first = reader.read_head_bytes().unwrap();
+            assert_eq!(
+                first, b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                "{}",
+                size
+            );
+            let second = reader.read_head_bytes().unwrap();
+            assert_eq!(
+                second, b"GET /b HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                "{}",
+                size
+            );
+            assert_eq!(reader.fill_buf().unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn fill_buf_reports_would_block_until_data_arrives() {
+        let mut reader =
+            RequestReader::from_reader(SlowReader::new("GET / HTTP/1.1\r\n", 4).with_would_block());
+
+        let e = reader.fill_buf().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        // Nothing consumed, the data comes with the next attempt
+        assert_eq!(reader.fill_buf().unwrap(), 4);
+        assert_eq!(reader.peek(), b"GET ");
+        assert_eq!(reader.reader.reads(), 2);
+    }
+
+    #[test]
+    fn read_line_reads_in_blocks() {
+        let headers = "X-Header: value\r\n".repeat(50);
+        let message = format!("GET / HTTP/1.1\r\n{}\r\n", headers);
+        let mut reader = RequestReader::from_reader(SlowReader::new(message.clone(), usize::MAX));
+
+        reader.read_start_line().unwrap();
+        assert_eq!(reader.read_headers().unwrap().len(), 50);
+
+        // One read per buffered block rather than per line or per few bytes
+        let reads = reader.reader.reads();
+        assert!(
+            reads <= message.len().div_ceil(BUFFERED_READER_BUF_SIZE),
+            "{}",
+            reads
+        );
+    }
+
+    #[test]
+    fn into_inner_returns_reader_and_leftover_bytes() {
+        let message = b"GET /chat HTTP/1.1\r\nUpgrade: websocket\r\n\r\n\x81\x05hello";
+        let mut reader = RequestReader::from_reader(&message[..]);
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+
+        let (rest, buffered) = reader.into_inner();
+
+        // Everything was read in one block, the frame sent after the head is left buffered
+        assert_eq!(buffered, b"\x81\x05hello");
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn from_buf_read_finds_patterns_across_buffer_refills() {
+        // A tiny buffer makes delimiters straddle refills
+        let data = "GET / HTTP/1.1\r\nHost: a\r\nX-Long: abcdefghij\r\n\r\nbody".as_bytes();
+        let mut reader = RequestReader::from_buf_read(io::BufReader::with_capacity(3, data));
+
+        assert_eq!(reader.read_start_line().unwrap(), "GET / HTTP/1.1");
+        assert_eq!(
+            reader.read_headers().unwrap(),
+            ["Host: a", "X-Long: abcdefghij"]
+        );
+        // Nothing past the head was consumed from the underlying reader
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "body");
+    }
+
+    #[test]
+    fn from_buf_read_matches_from_reader() {
+        let message =
+            "POST /a HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhelloGET /b HTTP/1.1\r\n";
+        for capacity in [1, 2, 7, 64] {
+            let mut buffered = RequestReader::from_buf_read(io::BufReader::with_capacity(
+                capacity,
+                message.as_bytes(),
+            ));
+            let mut plain = RequestReader::from_reader(message.as_bytes());
+
+            assert_eq!(
+                buffered.read_head_bytes().unwrap(),
+                plain.read_head_bytes().unwrap()
+            );
+            let mut body = [0; 5];
+            buffered.read_exact(&mut body).unwrap();
+            assert_eq!(&body, b"hello");
+            assert_eq!(
+                buffered.read_line().unwrap(),
+                "GET /b HTTP/1.1",
+                "{}",
+                capacity
+            );
+        }
+    }
+
+    #[test]
+    fn from_buf_read_enforces_limits() {
+        let message = format!("GET / HTTP/1.1\r\nX-Huge: {}\r\n\r\n", "a".repeat(4096));
+        let mut reader = RequestReader::from_buf_read(io::BufReader::new(message.as_bytes()));
+        reader.set_limits(Limits {
+            max_header_line: 1024,
+            ..Default::default()
+        });
+
+        reader.read_start_line().unwrap();
+        let e = reader.read_headers().expect_err("expected error");
+        assert!(matches!(
+            e,
+            RequestReaderError::HeaderLineTooLong { limit: 1024 }
+        ));
+
+        let mut reader = RequestReader::from_buf_read(io::BufReader::new(&b"no delimiter"[..]));
+        let e = reader.read_line().expect_err("expected error");
+        assert!(matches!(e, RequestReaderError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    /// Compares reading a head through the internal buffer and through a `BufReader`, run with
+    /// `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_from_reader_vs_from_buf_read() {
+        const ITERATIONS: u32 = 100_000;
+        let headers = "X-Header: some moderately long header value\r\n".repeat(20);
+        let message = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n", headers);
+
+        let time = |f: &dyn Fn()| {
+            let start = std::time::Instant::now();
+            for _ in 0..ITERATIONS {
+                f();
+            }
+            start.elapsed() / ITERATIONS
+        };
+
+        let from_reader = time(&|| {
+            let mut reader = RequestReader::from_reader(message.as_bytes());
+            reader.read_start_line().unwrap();
+            std::hint::black_box(reader.read_headers().unwrap());
+        });
+        // A byte slice is its own buffer, so lines are copied straight out of it
+        let from_buf_read = time(&|| {
+            let mut reader = RequestReader::from_buf_read(message.as_bytes());
+            reader.read_start_line().unwrap();
+            std::hint::black_box(reader.read_headers().unwrap());
+        });
+        let from_buf_reader = time(&|| {
+            let mut reader = RequestReader::from_buf_read(io::BufReader::new(message.as_bytes()));
+            reader.read_start_line().unwrap();
+            std::hint::black_box(reader.read_headers().unwrap());
+        });
+
+        println!(
+            "from_reader: {:?}/iter, from_buf_read: {:?}/iter, from_buf_read with a BufReader: {:?}/iter",
+            from_reader, from_buf_read, from_buf_reader
+        );
+    }
 }
@@ -0,0 +1,742 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use super::{date, encoding, media, Headers, Request, Version};
+
+/// Headers holding credentials, never reflected in a `TRACE` response since scripts could
+/// otherwise read them through it (cross-site tracing).
+const TRACE_HIDDEN_HEADERS: [&str; 3] = ["Authorization", "Proxy-Authorization", "Cookie"];
+
+/// Size of the chunks a streamed body of unknown length is sent in.
+const STREAM_CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusCode {
+    Continue,
+    SwitchingProtocols,
+    Ok,
+    NoContent,
+    PartialContent,
+    MovedPermanently,
+    Found,
+    SeeOther,
+    NotModified,
+    TemporaryRedirect,
+    PermanentRedirect,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    NotAcceptable,
+    RequestTimeout,
+    PayloadTooLarge,
+    UriTooLong,
+    RangeNotSatisfiable,
+    ExpectationFailed,
+    TooManyRequests,
+    RequestHeaderFieldsTooLarge,
+    InternalServerError,
+    NotImplemented,
+    BadGateway,
+    ServiceUnavailable,
+    GatewayTimeout,
+    HttpVersionNotSupported,
+}
+
+impl StatusCode {
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Continue => 100,
+            Self::SwitchingProtocols => 101,
+            Self::Ok => 200,
+            Self::NoContent => 204,
+            Self::PartialContent => 206,
+            Self::MovedPermanently => 301,
+            Self::Found => 302,
+            Self::SeeOther => 303,
+            Self::NotModified => 304,
+            Self::TemporaryRedirect => 307,
+            Self::PermanentRedirect => 308,
+            Self::BadRequest => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+            Self::NotAcceptable => 406,
+            Self::RequestTimeout => 408,
+            Self::PayloadTooLarge => 413,
+            Self::UriTooLong => 414,
+            Self::RangeNotSatisfiable => 416,
+            Self::ExpectationFailed => 417,
+            Self::TooManyRequests => 429,
+            Self::RequestHeaderFieldsTooLarge => 431,
+            Self::InternalServerError => 500,
+            Self::NotImplemented => 501,
+            Self::BadGateway => 502,
+            Self::ServiceUnavailable => 503,
+            Self::GatewayTimeout => 504,
+            Self::HttpVersionNotSupported => 505,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Continue => "Continue",
+            Self::SwitchingProtocols => "Switching Protocols",
+            Self::Ok => "OK",
+            Self::NoContent => "No Content",
+            Self::PartialContent => "Partial Content",
+            Self::MovedPermanently => "Moved Permanently",
+            Self::Found => "Found",
+            Self::SeeOther => "See Other",
+            Self::NotModified => "Not Modified",
+            Self::TemporaryRedirect => "Temporary Redirect",
+            Self::PermanentRedirect => "Permanent Redirect",
+            Self::BadRequest => "Bad Request",
+            Self::Unauthorized => "Unauthorized",
+            Self::Forbidden => "Forbidden",
+            Self::NotFound => "Not Found",
+            Self::MethodNotAllowed => "Method Not Allowed",
+            Self::NotAcceptable => "Not Acceptable",
+            Self::RequestTimeout => "Request Timeout",
+            Self::PayloadTooLarge => "Payload Too Large",
+            Self::UriTooLong => "URI Too Long",
+            Self::RangeNotSatisfiable => "Range Not Satisfiable",
+            Self::ExpectationFailed => "Expectation Failed",
+            Self::TooManyRequests => "Too Many Requests",
+            Self::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            Self::InternalServerError => "Internal Server Error",
+            Self::NotImplemented => "Not Implemented",
+            Self::BadGateway => "Bad Gateway",
+            Self::ServiceUnavailable => "Service Unavailable",
+            Self::GatewayTimeout => "Gateway Timeout",
+            Self::HttpVersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+
+    /// Whether this is an interim (1xx) status, which is never followed by a body.
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.code())
+    }
+
+    /// Whether a response with this status can have a body. Interim responses, `204 No Content`
+    /// and `304 Not Modified` never do (RFC 7230 §3.3.3), so they are sent without a body or
+    /// `Content-Length`, and clients don't wait for one.
+    pub fn allows_body(&self) -> bool {
+        !self.is_informational() && !matches!(self, Self::NoContent | Self::NotModified)
+    }
+
+    /// Whether this status sends the client to the URI in `Location`. `304 Not Modified` isn't
+    /// one, despite its 3xx code.
+    pub fn is_redirect(&self) -> bool {
+        matches!(
+            self,
+            Self::MovedPermanently
+                | Self::Found
+                | Self::SeeOther
+                | Self::TemporaryRedirect
+                | Self::PermanentRedirect
+        )
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = u16;
+
+    /// Maps a numeric code to its variant, returning the code back if it isn't known.
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            100 => Ok(Self::Continue),
+            101 => Ok(Self::SwitchingProtocols),
+            200 => Ok(Self::Ok),
+            204 => Ok(Self::NoContent),
+            206 => Ok(Self::PartialContent),
+            301 => Ok(Self::MovedPermanently),
+            302 => Ok(Self::Found),
+            303 => Ok(Self::SeeOther),
+            304 => Ok(Self::NotModified),
+            307 => Ok(Self::TemporaryRedirect),
+            308 => Ok(Self::PermanentRedirect),
+            400 => Ok(Self::BadRequest),
+            401 => Ok(Self::Unauthorized),
+            403 => Ok(Self::Forbidden),
+            404 => Ok(Self::NotFound),
+            405 => Ok(Self::MethodNotAllowed),
+            406 => Ok(Self::NotAcceptable),
+            408 => Ok(Self::RequestTimeout),
+            413 => Ok(Self::PayloadTooLarge),
+            414 => Ok(Self::UriTooLong),
+            416 => Ok(Self::RangeNotSatisfiable),
+            417 => Ok(Self::ExpectationFailed),
+            429 => Ok(Self::TooManyRequests),
+            431 => Ok(Self::RequestHeaderFieldsTooLarge),
+            500 => Ok(Self::InternalServerError),
+            501 => Ok(Self::NotImplemented),
+            502 => Ok(Self::BadGateway),
+            503 => Ok(Self::ServiceUnavailable),
+            504 => Ok(Self::GatewayTimeout),
+            505 => Ok(Self::HttpVersionNotSupported),
+            _ => Err(value),
+        }
+    }
+}
+
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.code(), self.reason())
+    }
+}
+
+/// A body read from a stream while the response is written, instead of held in memory.
+struct StreamBody {
+    /// Taken by the first write, a stream can only be sent once.
+    reader: Mutex<Option<Box<dyn Read + Send>>>,
+    length: Option<u64>,
+}
+
+impl std::fmt::Debug for StreamBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamBody")
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    version: Version,
+    status: StatusCode,
+    headers: Headers,
+    body: Vec<u8>,
+    /// Replaces `body` when set. Clones share the stream.
+    stream: Option<Arc<StreamBody>>,
+}
+
+impl Response {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            version: Version::V1_1,
+            status,
+            headers: Headers::new(),
+            body: Vec::new(),
+            stream: None,
+        }
+    }
+
+    /// A `200 OK` with `body`.
+    pub fn ok_with(body: impl Into<Vec<u8>>) -> Self {
+        Self::new(StatusCode::Ok).with_body(body)
+    }
+
+    /// A `204 No Content`, written without a body or `Content-Length`.
+    pub fn no_content() -> Self {
+        Self::new(StatusCode::NoContent)
+    }
+
+    /// A `400 Bad Request` with the status as plain text body.
+    pub fn bad_request() -> Self {
+        Self::plain_status(StatusCode::BadRequest)
+    }
+
+    /// A `404 Not Found` with the status as plain text body.
+    pub fn not_found() -> Self {
+        Self::plain_status(StatusCode::NotFound)
+    }
+
+    /// A `500 Internal Server Error` with the status as plain text body.
+    pub fn internal_error() -> Self {
+        Self::plain_status(StatusCode::InternalServerError)
+    }
+
+    fn plain_status(status: StatusCode) -> Self {
+        Self::new(status)
+            .with_header("Content-Type", "text/plain")
+            .with_body(status.to_string())
+    }
+
+    /// Answer to a `TRACE` request, reflecting the request head back to the client as
+    /// `message/http`, without credential headers.
+    pub fn trace(request: &Request) -> Self {
+        let mut body = format!(
+            "{} {} {}\r\n",
+            request.method(),
+            request.target(),
+            request.version()
+        );
+        for (name, value) in request.headers().iter() {
+            if !TRACE_HIDDEN_HEADERS
+                .iter()
+                .any(|hidden| hidden.eq_ignore_ascii_case(name))
+            {
+                body.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+        body.push_str("\r\n");
+
+        Self::new(StatusCode::Ok)
+            .with_header("Content-Type", "message/http")
+            .with_body(body)
+    }
+
+    /// Redirects the client to `location`, escaping any character that isn't allowed in a URI
+    /// such as spaces, while keeping existing escapes.
+    ///
+    /// # Panics
+    ///
+    /// If `status` isn't a redirect status, see [`StatusCode::is_redirect`].
+    pub fn redirect(status: StatusCode, location: &str) -> Self {
+        assert!(status.is_redirect(), "{} is not a redirect status", status);
+        Self::new(status).with_header("Location", encoding::percent_encode_uri(location))
+    }
+
+    /// A `200 OK` streaming the file at `path`, with its length as `Content-Length` and a
+    /// `Content-Type` guessed from its extension (see [`media::guess_from_path`]).
+    ///
+    /// Errors if the file can't be opened, or with [`io::ErrorKind::NotFound`] if `path` isn't a
+    /// regular file, for the caller to answer with a 404 or 500.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "not a regular file",
+            ));
+        }
+
+        Ok(Self::new(StatusCode::Ok)
+            .with_header("Content-Type", media::guess_from_path(path))
+            .with_body_reader(file, Some(metadata.len())))
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// The in-memory body, empty for a body streamed with [`Response::with_body_reader`].
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Returns the value of the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Appends a header, keeping any existing header with the same name.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self.stream = None;
+        self
+    }
+
+    /// Streams the body from `reader` as the response is written, such as to send a file without
+    /// loading it. With a known `length`, exactly that many bytes are sent after a
+    /// `Content-Length`; otherwise the body is sent with chunked transfer coding until `reader`
+    /// ends. The response can only be written once.
+    pub fn with_body_reader(
+        mut self,
+        reader: impl Read + Send + 'static,
+        length: Option<u64>,
+    ) -> Self {
+        self.body = Vec::new();
+        self.stream = Some(Arc::new(StreamBody {
+            reader: Mutex::new(Some(Box::new(reader))),
+            length,
+        }));
+        self
+    }
+
+    /// Sets a header, replacing every existing header with the same name.
+    pub fn set_header(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.headers.set(name, value);
+    }
+
+    /// Serializes the response to `w`. `Date` and `Content-Length` headers are added unless they
+    /// were already set or the status is informational. A streamed body of unknown length gets a
+    /// `Transfer-Encoding: chunked` header instead of `Content-Length`, and a `Transfer-Encoding`
+    /// set by the handler is followed as is, without `Content-Length`.
+    ///
+    /// `w` is flushed once the response is written, so a buffered writer can't hold back the end
+    /// of a response while the server waits for the client's next request.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_to_at(w, SystemTime::now(), true)?;
+        w.flush()
+    }
+
+    /// Serializes the response as the answer to a `HEAD` request: the same headers as
+    /// [`Response::write_to`], `Content-Length` included, but no body. `w` is flushed as well.
+    pub fn write_head_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_to_at(w, SystemTime::now(), false)?;
+        w.flush()
+    }
+
+    /// Same as [`Response::write_to`], with the `Date` header set from `now`, and the body only
+    /// written if `with_body`.
+    fn write_to_at<W: Write>(&self, w: &mut W, now: SystemTime, with_body: bool) -> io::Result<()> {
+        let stream = match &self.stream {
+            Some(stream) => {
+                let reader = stream
+                    .reader
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .ok_or_else(|| io::Error::other("streamed body was already written"))?;
+                Some((reader, stream.length))
+            }
+            None => None,
+        };
+
+        write!(w, "{} {}\r\n", self.version, self.status)?;
+        // Interim and 204 responses can't carry framing headers at all (RFC 7230 §3.3.1, §3.3.2),
+        // unlike a 304 whose `Content-Length` is that of the resource
+        let framed = !self.status.is_informational() && self.status != StatusCode::NoContent;
+        for (name, value) in self.headers.iter() {
+            let framing = name.eq_ignore_ascii_case("Content-Length")
+                || name.eq_ignore_ascii_case("Transfer-Encoding");
+            if framing && !framed {
+                continue;
+            }
+            write!(w, "{}: {}\r\n", name, value)?;
+        }
+        if !self.status.is_informational() {
+            if !self.headers.contains("Date") {
+                write!(w, "Date: {}\r\n", date::format(now))?;
+            }
+            match &stream {
+                _ if !self.status.allows_body() => {}
+                // Framing chosen by the handler, a `Content-Length` would conflict with it
+                _ if self.transfer_encoding_is_chunked().is_some() => {}
+                Some((_, None)) => write!(w, "Transfer-Encoding: chunked\r\n")?,
+                _ if self.headers.contains("Content-Length") => {}
+                Some((_, Some(length))) => write!(w, "Content-Length: {}\r\n", length)?,
+                None => write!(w, "Content-Length: {}\r\n", self.body.len())?,
+            }
+        }
+        w.write_all(b"\r\n")?;
+        if !with_body || !self.status.allows_body() {
+            return Ok(());
+        }
+
+        match (stream, self.transfer_encoding_is_chunked()) {
+            (None, Some(true)) => write_chunked(&mut self.body.as_slice(), w),
+            (None, _) => w.write_all(&self.body),
+            (Some((mut reader, _)), Some(true)) => write_chunked(&mut reader, w),
+            (Some((mut reader, _)), Some(false)) => io::copy(&mut reader, w).map(drop),
+            (Some((reader, Some(length))), None) => {
+                let sent = io::copy(&mut reader.take(length), w)?;
+                if sent < length {
+                    // The length was already announced, the response can't be completed
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(())
+            }
+            (Some((mut reader, None)), None) => write_chunked(&mut reader, w),
+        }
+    }
+
+    /// Whether the last coding of a `Transfer-Encoding` set by the handler is `chunked`, `None`
+    /// without the header.
+    fn transfer_encoding_is_chunked(&self) -> Option<bool> {
+        let value = self.headers.get_all("Transfer-Encoding").last()?;
+        let last = value.rsplit(',').next().unwrap_or_default();
+        Some(last.trim().eq_ignore_ascii_case("chunked"))
+    }
+
+    /// Whether the body can only be delimited by closing the connection, as for a
+    /// `Transfer-Encoding` not ending with `chunked`. The connection can't be kept alive after
+    /// such a response.
+    pub fn is_close_delimited(&self) -> bool {
+        self.status.allows_body() && self.transfer_encoding_is_chunked() == Some(false)
+    }
+}
+
+/// Writes everything from `reader` as chunks, then the last chunk ending the body.
+fn write_chunked<W: Write>(reader: &mut dyn Read, w: &mut W) -> io::Result<()> {
+    let mut buf = vec![0; STREAM_CHUNK_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            return w.write_all(b"0\r\n\r\n");
+        }
+        write!(w, "{:x}\r\n", n)?;
+        w.write_all(&buf[..n])?;
+        w.write_all(b"\r\n")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `response` as of `Sun, 06 Nov 1994 08:49:37 GMT`.
+    fn to_string(response: &Response) -> String {
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(784_111_777);
+        let mut out = Vec::new();
+        response
+            .write_to_at(&mut out, now, true)
+            .expect("error writing response");
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn response_writes_status_headers_and_body() {
+        let response = Response::new(StatusCode::Ok)
+            .with_header("Content-Type", "text/plain")
+            .with_body("hello");
+
+        assert_eq!(
+            to_string(&response),
+            "HTTP/1.1 200 OK\r
+Content-Type: text/plain\r
+Date: Sun, 06 Nov 1994 08:49:37 GMT\r
+Content-Length: 5\r
+\r
+hello"
+        );
+    }
+
+    #[test]
+    fn response_writes_no_content_without_body_or_length() {
+        // Even with a body set by mistake, none is written
+        for response in [
+            Response::no_content(),
+            Response::no_content().with_body("ignored"),
+            Response::new(StatusCode::NotModified).with_body("ignored"),
+            Response::no_content().with_header("Content-Length", "5"),
+            Response::no_content().with_header("Transfer-Encoding", "chunked"),
+        ] {
+            let status = response.status();
+            assert_eq!(
+                to_string(&response),
+                format!(
+                    "HTTP/1.1 {}\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n",
+                    status
+                )
+            );
+            assert!(!response.is_close_delimited());
+        }
+
+        let streamed = Response::no_content()
+            .with_body_reader(std::io::Cursor::new(b"ignored".to_vec()), None);
+        assert!(to_string(&streamed).ends_with("GMT\r\n\r\n"));
+    }
+
+    #[test]
+    fn response_shortcuts_set_status_and_body() {
+        let ok = Response::ok_with("hello");
+        assert_eq!(ok.status(), StatusCode::Ok);
+        assert_eq!(ok.body(), b"hello");
+
+        for (response, status) in [
+            (Response::bad_request(), StatusCode::BadRequest),
+            (Response::not_found(), StatusCode::NotFound),
+            (Response::internal_error(), StatusCode::InternalServerError),
+        ] {
+            assert_eq!(response.status(), status);
+            assert_eq!(response.header("Content-Type"), Some("text/plain"));
+            assert_eq!(response.body(), status.to_string().as_bytes());
+        }
+        assert!(Response::no_content().body().is_empty());
+    }
+
+    #[test]
+    fn response_writes_interim_status_without_length() {
+        let response = Response::new(StatusCode::Continue);
+
+        assert_eq!(to_string(&response), "HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn response_keeps_explicit_content_length() {
+        let response = Response::new(StatusCode::Ok).with_header("Content-Length", "0");
+
+        assert_eq!(
+            to_string(&response),
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn response_streams_body_of_known_length() {
+        let body = "x".repeat(20 * 1024);
+        let response = Response::new(StatusCode::Ok)
+            .with_body_reader(io::Cursor::new(body.clone()), Some(body.len() as u64));
+
+        let out = to_string(&response);
+
+        assert!(out.contains("\r\nContent-Length: 20480\r\n\r\n"));
+        assert!(out.ends_with(&format!("\r\n\r\n{}", body)));
+        assert!(response.write_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn response_streams_body_of_unknown_length_as_chunks() {
+        let body = "y".repeat(STREAM_CHUNK_SIZE + 10);
+        let response = Response::new(StatusCode::Ok).with_body_reader(io::Cursor::new(body), None);
+
+        let out = to_string(&response);
+
+        assert_eq!(
+            out,
+            format!(
+                "HTTP/1.1 200 OK\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n\
+                 Transfer-Encoding: chunked\r\n\r\n2000\r\n{}\r\na\r\n{}\r\n0\r\n\r\n",
+                "y".repeat(STREAM_CHUNK_SIZE),
+                "y".repeat(10)
+            )
+        );
+    }
+
+    #[test]
+    fn response_write_to_flushes_buffered_writer() {
+        let response = Response::new(StatusCode::Ok).with_body("complete body");
+
+        let mut out = io::BufWriter::with_capacity(64 * 1024, Vec::new());
+        response.write_to(&mut out).unwrap();
+        // Without a flush, everything would still sit in the BufWriter
+        let written = String::from_utf8(out.get_ref().clone()).unwrap();
+        assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(written.ends_with("\r\n\r\ncomplete body"));
+
+        let mut out = io::BufWriter::with_capacity(64 * 1024, Vec::new());
+        response.write_head_to(&mut out).unwrap();
+        assert!(out.get_ref().ends_with(b"Content-Length: 13\r\n\r\n"));
+    }
+
+    #[test]
+    fn response_from_file_streams_with_length_and_type() {
+        let path = std::env::temp_dir().join(format!("simple_http-{}.txt", std::process::id()));
+        std::fs::write(&path, "file contents").unwrap();
+
+        let response = Response::from_file(&path).unwrap();
+        let mut out = Vec::new();
+        response.write_to(&mut out).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\r\nContent-Type: text/plain; charset=utf-8\r\n"));
+        assert!(out.contains("\r\nContent-Length: 13\r\n"));
+        assert!(out.ends_with("\r\n\r\nfile contents"));
+    }
+
+    #[test]
+    fn response_from_file_errors_when_missing_or_directory() {
+        let missing = std::env::temp_dir().join("simple_http-missing-file");
+        let e = Response::from_file(missing).expect_err("expected error");
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+        let e = Response::from_file(std::env::temp_dir()).expect_err("expected error");
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn response_redirects_to_escaped_location() {
+        let response = Response::redirect(StatusCode::Found, "/new place?q=café&page=2");
+
+        assert_eq!(
+            to_string(&response),
+            "HTTP/1.1 302 Found\r\nLocation: /new%20place?q=caf%C3%A9&page=2\r\n\
+             Date: Sun, 06 Nov 1994 08:49:37 GMT\r\nContent-Length: 0\r\n\r\n"
+        );
+        assert_eq!(
+            Response::redirect(StatusCode::PermanentRedirect, "https://example.com/a%20b")
+                .header("Location"),
+            Some("https://example.com/a%20b")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not a redirect status")]
+    fn response_redirect_rejects_other_statuses() {
+        Response::redirect(StatusCode::NotModified, "/");
+    }
+
+    #[test]
+    fn response_without_length_is_close_delimited() {
+        let response = Response::new(StatusCode::Ok)
+            .with_header("Transfer-Encoding", "gzip")
+            .with_body("abc");
+
+        assert!(response.is_close_delimited());
+        assert_eq!(
+            to_string(&response),
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\n\
+             Date: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\nabc"
+        );
+        assert!(!Response::new(StatusCode::Ok).is_close_delimited());
+    }
+
+    #[test]
+    fn response_set_header_replaces_existing() {
+        let mut response = Response::new(StatusCode::Ok).with_header("connection", "keep-alive");
+        response.set_header("Connection", "close");
+
+        assert_eq!(response.headers().len(), 1);
+        assert_eq!(response.header("connection"), Some("close"));
+    }
+
+    #[test]
+    fn status_code_converts_from_number() {
+        assert_eq!(StatusCode::try_from(404), Ok(StatusCode::NotFound));
+        assert_eq!(StatusCode::try_from(431).map(|s| s.code()), Ok(431));
+        assert_eq!(StatusCode::try_from(504), Ok(StatusCode::GatewayTimeout));
+        assert_eq!(StatusCode::try_from(299), Err(299));
+    }
+
+    #[test]
+    fn trace_reflects_request_without_credentials() {
+        let request = Request::parse(
+            b"TRACE /path HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic abc\r\nCookie: a=b\r\nX-Test: 1\r\n\r\n",
+        )
+        .unwrap();
+        let response = Response::trace(&request);
+
+        assert_eq!(response.header("Content-Type"), Some("message/http"));
+        assert_eq!(
+            response.body(),
+            b"TRACE /path HTTP/1.1\r\nHost: localhost\r\nX-Test: 1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn response_keeps_explicit_date() {
+        let response =
+            Response::new(StatusCode::Ok).with_header("Date", "Thu, 01 Jan 1970 00:00:00 GMT");
+
+        assert_eq!(
+            to_string(&response),
+            "HTTP/1.1 200 OK\r\nDate: Thu, 01 Jan 1970 00:00:00 GMT\r\nContent-Length: 0\r\n\r\n"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Components of the request target, in the forms of RFC 7230 §5.3.
+
+use super::{encoding, Request};
+
+/// Which of the request target forms a target is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetForm {
+    /// A path and optional query, like `/index.html?lang=en`. Used by most requests.
+    Origin,
+    /// A full URI, like `http://example.com/index.html`, sent to proxies.
+    Absolute,
+    /// Only a host and port, like `example.com:443`, sent with `CONNECT`.
+    Authority,
+    /// A single `*`, sent with server-wide `OPTIONS` requests.
+    Asterisk,
+}
+
+/// A request target split into its components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target<'a> {
+    form: TargetForm,
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> Target<'a> {
+    pub fn parse(target: &'a str) -> Self {
+        if target == "*" {
+            return Self::new(TargetForm::Asterisk, None, None, "*");
+        }
+        if target.starts_with('/') {
+            return Self::new(TargetForm::Origin, None, None, target);
+        }
+        match split_scheme(target) {
+            Some((scheme, rest)) => {
+                let end = rest.find(['/', '?']).unwrap_or(rest.len());
+                let (authority, path) = rest.split_at(end);
+                Self::new(TargetForm::Absolute, Some(scheme), Some(authority), path)
+            }
+            None => Self {
+                form: TargetForm::Authority,
+                scheme: None,
+                authority: Some(target),
+                path: "",
+                query: None,
+            },
+        }
+    }
+
+    fn new(
+        form: TargetForm,
+        scheme: Option<&'a str>,
+        authority: Option<&'a str>,
+        path_and_query: &'a str,
+    ) -> Self {
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+        Self {
+            form,
+            scheme,
+            authority,
+            path,
+            query,
+        }
+    }
+
+    pub fn form(&self) -> TargetForm {
+        self.form
+    }
+
+    /// Scheme of an absolute-form target, such as `http`.
+    pub fn scheme(&self) -> Option<&'a str> {
+        self.scheme
+    }
+
+    /// Host and optional port of an absolute-form or authority-form target.
+    pub fn authority(&self) -> Option<&'a str> {
+        self.authority
+    }
+
+    /// Path of the target, `/` for an absolute-form target without one and empty for an
+    /// authority-form target.
+    pub fn path(&self) -> &'a str {
+        match self.form {
+            TargetForm::Absolute if self.path.is_empty() => "/",
+            _ => self.path,
+        }
+    }
+
+    /// Query string after the `?`, without it.
+    pub fn query(&self) -> Option<&'a str> {
+        self.query
+    }
+}
+
+/// Splits `scheme://rest`, the scheme being a letter followed by letters, digits, `+`, `-` or
+/// `.`.
+fn split_scheme(target: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = target.split_once("://")?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some((scheme, rest))
+}
+
+impl Request {
+    /// The request target split into its components.
+    pub fn parsed_target(&self) -> Target<'_> {
+        Target::parse(self.target())
+    }
+
+    /// Path of the request target, without the query string.
+    pub fn path(&self) -> &str {
+        self.parsed_target().path()
+    }
+
+    /// Query string of the request target, without the `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.parsed_target().query()
+    }
+
+    /// Decoded value of the first `name` parameter in the query string, `name` being matched
+    /// against the decoded keys. `None` if the parameter or the query string is missing.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query_param_all(name).into_iter().next()
+    }
+
+    /// Decoded values of every `name` parameter in the query string, in order.
+    pub fn query_param_all(&self, name: &str) -> Vec<String> {
+        query_pairs(self.query().unwrap_or_default())
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .collect()
+    }
+}
+
+/// Decoded `key=value` pairs of a query string, `+` standing for a space as in HTML forms. A key
+/// without `=` has an empty value, and pairs with a malformed escape are skipped.
+fn query_pairs(query: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    let decode = |s: &str| encoding::percent_decode(&s.replace('+', " "));
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(move |pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((decode(key)?, decode(value)?))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    #[test]
+    fn target_parses_origin_form() {
+        let target = Target::parse("/search?q=rust");
+
+        assert_eq!(target.form(), TargetForm::Origin);
+        assert_eq!(target.scheme(), None);
+        assert_eq!(target.authority(), None);
+        assert_eq!(target.path(), "/search");
+        assert_eq!(target.query(), Some("q=rust"));
+    }
+
+    #[test]
+    fn target_parses_absolute_form() {
+        let target = Target::parse("http://example.com:8080/a/b?x=1&y=2");
+
+        assert_eq!(target.form(), TargetForm::Absolute);
+        assert_eq!(target.scheme(), Some("http"));
+        assert_eq!(target.authority(), Some("example.com:8080"));
+        assert_eq!(target.path(), "/a/b");
+        assert_eq!(target.query(), Some("x=1&y=2"));
+    }
+
+    #[test]
+    fn target_defaults_absolute_form_path_to_root() {
+        let target = Target::parse("https://example.com?x=1");
+
+        assert_eq!(target.authority(), Some("example.com"));
+        assert_eq!(target.path(), "/");
+        assert_eq!(target.query(), Some("x=1"));
+    }
+
+    #[test]
+    fn target_parses_authority_and_asterisk_forms() {
+        let target = Target::parse("example.com:443");
+        assert_eq!(target.form(), TargetForm::Authority);
+        assert_eq!(target.authority(), Some("example.com:443"));
+
+        assert_eq!(Target::parse("*").form(), TargetForm::Asterisk);
+    }
+
+    fn with_target(target: &str) -> Request {
+        Request::builder(Method::Get, target).build()
+    }
+
+    #[test]
+    fn query_param_is_none_without_key_or_query() {
+        assert_eq!(with_target("/search").query_param("q"), None);
+        assert_eq!(with_target("/search?page=2").query_param("q"), None);
+        assert!(with_target("/search").query_param_all("q").is_empty());
+    }
+
+    #[test]
+    fn query_param_decodes_key_and_value() {
+        let request = with_target("/search?q=caf%C3%A9+au+lait&sort%20by=date&flag");
+
+        assert_eq!(request.query_param("q").as_deref(), Some("café au lait"));
+        assert_eq!(request.query_param("sort by").as_deref(), Some("date"));
+        assert_eq!(request.query_param("flag").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn query_param_all_returns_repeated_keys() {
+        let request = with_target("/items?tag=a&bad=%zz&tag=b&other=1&tag=");
+
+        assert_eq!(request.query_param("tag").as_deref(), Some("a"));
+        assert_eq!(request.query_param_all("tag"), ["a", "b", ""]);
+        assert_eq!(request.query_param("bad"), None);
+    }
+
+    #[test]
+    fn absolute_form_authority_overrides_host_header() {
+        let request = Request::builder(Method::Get, "http://example.com/path?q")
+            .with_header("Host", "other.org")
+            .build();
+
+        assert_eq!(request.host(), Some("example.com"));
+        assert_eq!(request.path(), "/path");
+        assert_eq!(request.query(), Some("q"));
+    }
+
+    #[test]
+    fn set_target_rewrites_components() {
+        let mut request = Request::builder(Method::Get, "/old/page?lang=en").build();
+
+        let rewritten = request.path().replacen("/old", "/new", 1);
+        request
+            .set_target(format!("{}?lang=fr", rewritten))
+            .unwrap();
+
+        assert_eq!(request.target(), "/new/page?lang=fr");
+        assert_eq!(request.path(), "/new/page");
+        assert_eq!(request.query(), Some("lang=fr"));
+        assert_eq!(request.query_param("lang").as_deref(), Some("fr"));
+    }
+
+    #[test]
+    fn set_target_rejects_invalid_targets() {
+        let mut request = Request::builder(Method::Get, "/old").build();
+
+        for invalid in ["", "*", "/has space", "/nul\0"] {
+            assert!(request.set_target(invalid).is_err(), "{:?}", invalid);
+        }
+        assert_eq!(request.target(), "/old");
+    }
+}
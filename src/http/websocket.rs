@@ -0,0 +1,259 @@
+//! Server side of the WebSocket opening handshake (RFC 6455 §4.2). Only the handshake is handled,
+//! framing is left to the caller once the connection is upgraded.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Write},
+};
+
+use super::{base64, Method, Request, Response, StatusCode, Version};
+
+/// Fixed GUID appended to the client key when computing `Sec-WebSocket-Accept`.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Debug)]
+pub enum WebSocketError {
+    /// The request isn't a `GET` over HTTP/1.1 asking to upgrade to `websocket`.
+    NotUpgrade,
+    UnsupportedVersion(Option<String>),
+    InvalidKey,
+    Io(io::Error),
+}
+
+impl Display for WebSocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotUpgrade => write!(f, "request is not a websocket upgrade"),
+            Self::UnsupportedVersion(Some(v)) => {
+                write!(f, "unsupported websocket version: {}", v)
+            }
+            Self::UnsupportedVersion(None) => write!(f, "missing websocket version"),
+            Self::InvalidKey => write!(f, "missing or invalid Sec-WebSocket-Key"),
+            Self::Io(e) => write!(f, "IO error writing the handshake: {}", e),
+        }
+    }
+}
+
+impl Error for WebSocketError {}
+
+impl From<io::Error> for WebSocketError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Computes the `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut input = key.as_bytes().to_vec();
+    input.extend(WEBSOCKET_GUID.as_bytes());
+    base64::encode(&sha1(&input))
+}
+
+impl Request {
+    /// Whether this is a websocket opening handshake this module can answer: a `GET` over
+    /// HTTP/1.1 with `Connection: upgrade`, `Upgrade: websocket`, `Sec-WebSocket-Version: 13` and
+    /// a `Sec-WebSocket-Key`. The key itself is only checked by [`handshake_response`].
+    pub fn is_websocket_upgrade(&self) -> bool {
+        asks_for_websocket(self)
+            && self.header("Sec-WebSocket-Version") == Some("13")
+            && self.header("Sec-WebSocket-Key").is_some()
+    }
+}
+
+/// Whether `request` is a `GET` over HTTP/1.1 asking to upgrade to `websocket`.
+fn asks_for_websocket(request: &Request) -> bool {
+    let wants_websocket = request.header("Upgrade").is_some_and(|value| {
+        value
+            .split(',')
+            .any(|t| t.trim().eq_ignore_ascii_case("websocket"))
+    });
+    request.method() == Method::Get
+        && request.version() == Version::V1_1
+        && request.wants_upgrade()
+        && wants_websocket
+}
+
+/// Validates the upgrade request and builds the `101 Switching Protocols` response.
+pub fn handshake_response(request: &Request) -> Result<Response, WebSocketError> {
+    if !asks_for_websocket(request) {
+        return Err(WebSocketError::NotUpgrade);
+    }
+
+    match request.header("Sec-WebSocket-Version") {
+        Some("13") => {}
+        v => return Err(WebSocketError::UnsupportedVersion(v.map(ToOwned::to_owned))),
+    }
+
+    // The key must be a base64-encoded 16 byte nonce
+    let key = request
+        .header("Sec-WebSocket-Key")
+        .ok_or(WebSocketError::InvalidKey)?;
+    if base64::decode(key).is_none_or(|nonce| nonce.len() != 16) {
+        return Err(WebSocketError::InvalidKey);
+    }
+
+    Ok(Response::new(StatusCode::SwitchingProtocols)
+        .with_header("Upgrade", "websocket")
+        .with_header("Connection", "Upgrade")
+        .with_header("Sec-WebSocket-Accept", accept_key(key)))
+}
+
+/// Writes the handshake response to `stream` and hands it back, ready for websocket framing.
+pub fn upgrade<S: Write>(request: &Request, mut stream: S) -> Result<S, WebSocketError> {
+    let response = handshake_response(request)?;
+    response.write_to(&mut stream)?;
+    Ok(stream)
+}
+
+/// SHA-1 digest (FIPS 180-4). Only used for the handshake, which doesn't rely on its collision
+/// resistance.
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((input.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (chunk, v) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&v.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::RequestReader;
+
+    fn parse(message: &str) -> Request {
+        let mut reader = RequestReader::from_reader(message.as_bytes());
+        Request::try_from_reader(&mut reader).expect("error parsing request")
+    }
+
+    const UPGRADE_REQUEST: &str = "GET /chat HTTP/1.1\r
+Host: server.example.com\r
+Upgrade: websocket\r
+Connection: Upgrade\r
+Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r
+Sec-WebSocket-Version: 13\r
+\r
+";
+
+    #[test]
+    fn sha1_matches_known_digest() {
+        let digest = sha1(b"abc");
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+
+        assert_eq!(hex, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn upgrade_writes_switching_protocols() {
+        let request = parse(UPGRADE_REQUEST);
+        let out = upgrade(&request, Vec::new()).expect("error upgrading connection");
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 101 Switching Protocols\r
+Upgrade: websocket\r
+Connection: Upgrade\r
+Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r
+\r
+"
+        );
+    }
+
+    #[test]
+    fn is_websocket_upgrade_requires_every_condition() {
+        assert!(parse(UPGRADE_REQUEST).is_websocket_upgrade());
+        assert!(parse(
+            &UPGRADE_REQUEST.replace("Connection: Upgrade", "Connection: keep-alive, upgrade")
+        )
+        .is_websocket_upgrade());
+
+        let near_misses = [
+            UPGRADE_REQUEST.replace("GET", "POST"),
+            UPGRADE_REQUEST.replace("HTTP/1.1", "HTTP/1.0"),
+            UPGRADE_REQUEST.replace("Connection: Upgrade", "Connection: keep-alive"),
+            UPGRADE_REQUEST.replace("Upgrade: websocket", "Upgrade: h2c"),
+            UPGRADE_REQUEST.replace("Version: 13", "Version: 8"),
+            UPGRADE_REQUEST.replace("Sec-WebSocket-Version: 13\r\n", ""),
+            UPGRADE_REQUEST.replace("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n", ""),
+        ];
+        for message in near_misses {
+            assert!(!parse(&message).is_websocket_upgrade(), "{}", message);
+        }
+    }
+
+    #[test]
+    fn handshake_errors_when_not_upgrade() {
+        let request = parse("GET /chat HTTP/1.1\r\nHost: server.example.com\r\n\r\n");
+        let e = handshake_response(&request).expect_err("expected error for plain request");
+
+        assert!(matches!(e, WebSocketError::NotUpgrade));
+    }
+
+    #[test]
+    fn handshake_errors_when_invalid_key() {
+        let request = parse(&UPGRADE_REQUEST.replace("dGhlIHNhbXBsZSBub25jZQ==", "c2hvcnQ="));
+        let e = handshake_response(&request).expect_err("expected error for short key");
+
+        assert!(matches!(e, WebSocketError::InvalidKey));
+    }
+
+    #[test]
+    fn handshake_errors_when_unsupported_version() {
+        let request = parse(&UPGRADE_REQUEST.replace("Version: 13", "Version: 8"));
+        let e = handshake_response(&request).expect_err("expected error for old version");
+
+        assert!(matches!(e, WebSocketError::UnsupportedVersion(Some(_))));
+    }
+}
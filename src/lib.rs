@@ -1 +1,5 @@
+pub mod clock;
+pub mod cors;
 pub mod http;
+pub mod router;
+pub mod server;
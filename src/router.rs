@@ -0,0 +1,277 @@
+//! Dispatching requests to handlers by path and method.
+
+use crate::{
+    http::{Method, Request, Response, StatusCode},
+    server::{Handler, HandlerError},
+};
+
+/// Handlers registered for one path.
+struct Route {
+    path: String,
+    handlers: Vec<(Method, Box<dyn Handler>)>,
+}
+
+/// A [`Handler`] calling the handler registered for the request's path and method. Paths are
+/// matched exactly, ignoring the query string.
+///
+/// Requests with a method no route handles get a `501 Not Implemented`, requests for an unknown
+/// path get a `404 Not Found`, and requests for a known path with another method get a
+/// `405 Method Not Allowed` listing the registered methods in `Allow`. `OPTIONS *` is answered
+/// directly, with every method the router handles, and so is `OPTIONS` on a known path without
+/// an `OPTIONS` handler, with the methods registered for that path. `HEAD` requests without a
+/// handler of their own go to the `GET` handler.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `method` requests to `path`, replacing any handler previously
+    /// registered for both.
+    pub fn route(mut self, method: Method, path: impl Into<String>, handler: impl Handler) -> Self {
+        let path = path.into();
+        let index = match self.routes.iter().position(|r| r.path == path) {
+            Some(index) => index,
+            None => {
+                self.routes.push(Route {
+                    path,
+                    handlers: Vec::new(),
+                });
+                self.routes.len() - 1
+            }
+        };
+
+        let handlers = &mut self.routes[index].handlers;
+        let handler: Box<dyn Handler> = Box::new(handler);
+        match handlers.iter_mut().find(|(m, _)| *m == method) {
+            Some(existing) => existing.1 = handler,
+            None => handlers.push((method, handler)),
+        }
+        self
+    }
+
+    pub fn get(self, path: impl Into<String>, handler: impl Handler) -> Self {
+        self.route(Method::Get, path, handler)
+    }
+
+    pub fn post(self, path: impl Into<String>, handler: impl Handler) -> Self {
+        self.route(Method::Post, path, handler)
+    }
+
+    pub fn put(self, path: impl Into<String>, handler: impl Handler) -> Self {
+        self.route(Method::Put, path, handler)
+    }
+
+    pub fn delete(self, path: impl Into<String>, handler: impl Handler) -> Self {
+        self.route(Method::Delete, path, handler)
+    }
+
+    /// Handler of `route` for `method`. HEAD is answered like GET when it has no handler of its
+    /// own, the server leaves the body out.
+    fn handler(route: &Route, method: Method) -> Option<&dyn Handler> {
+        let find = |method| {
+            route
+                .handlers
+                .iter()
+                .find(|(m, _)| *m == method)
+                .map(|(_, handler)| &**handler)
+        };
+        find(method).or_else(|| {
+            (method == Method::Head)
+                .then(|| find(Method::Get))
+                .flatten()
+        })
+    }
+
+    /// Value of the `Allow` header for a route, ie: `GET, POST`.
+    fn allow(route: &Route) -> String {
+        route
+            .handlers
+            .iter()
+            .map(|(method, _)| method.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Response to `OPTIONS *`, listing the methods registered on any path, and `OPTIONS` itself.
+    fn server_options(&self) -> Response {
+        let mut methods = Vec::new();
+        let registered = self.routes.iter().flat_map(|r| r.handlers.iter());
+        for method in registered
+            .map(|(method, _)| *method)
+            .chain([Method::Options])
+        {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        let allow = methods
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Response::new(StatusCode::Ok).with_header("Allow", allow)
+    }
+}
+
+impl Handler for Router {
+    fn handle(&self, request: &Request) -> Result<Response, HandlerError> {
+        if request.method() == Method::Options && request.target() == "*" {
+            return Ok(self.server_options());
+        }
+
+        let method = request.method();
+        let implemented = self
+            .routes
+            .iter()
+            .any(|r| Self::handler(r, method).is_some());
+        if !implemented && method != Method::Options {
+            return Ok(Response::new(StatusCode::NotImplemented));
+        }
+
+        let path = request.path();
+        let Some(route) = self.routes.iter().find(|r| r.path == path) else {
+            return Ok(Response::new(StatusCode::NotFound));
+        };
+
+        match Self::handler(route, method) {
+            Some(handler) => handler.handle(request),
+            None if method == Method::Options => {
+                let allow = format!("{}, {}", Self::allow(route), Method::Options);
+                Ok(Response::new(StatusCode::Ok).with_header("Allow", allow))
+            }
+            None => Ok(Response::new(StatusCode::MethodNotAllowed)
+                .with_header("Allow", Self::allow(route))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, target: &str) -> Request {
+        let message = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, target);
+        Request::parse(message.as_bytes()).expect("error parsing request")
+    }
+
+    fn ok(body: &'static str) -> impl Handler {
+        move |_: &Request| Response::new(StatusCode::Ok).with_body(body)
+    }
+
+    #[test]
+    fn router_dispatches_by_path_and_method() {
+        let router = Router::new()
+            .get("/x", ok("get x"))
+            .post("/x", ok("post x"))
+            .get("/y", ok("get y"));
+
+        assert_eq!(
+            router.handle(&request("GET", "/x")).unwrap().body(),
+            b"get x"
+        );
+        assert_eq!(
+            router.handle(&request("POST", "/x")).unwrap().body(),
+            b"post x"
+        );
+        assert_eq!(
+            router.handle(&request("GET", "/y?page=2")).unwrap().body(),
+            b"get y"
+        );
+    }
+
+    #[test]
+    fn router_returns_not_found_for_unknown_path() {
+        let router = Router::new().get("/x", ok("x"));
+
+        let response = router.handle(&request("GET", "/z")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::NotFound);
+    }
+
+    #[test]
+    fn router_returns_method_not_allowed_with_allow_header() {
+        let router = Router::new().get("/x", ok("x")).post("/y", ok("y"));
+
+        let response = router.handle(&request("POST", "/x")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+        assert_eq!(response.header("Allow"), Some("GET"));
+    }
+
+    #[test]
+    fn router_allow_header_lists_every_registered_method() {
+        let router = Router::new()
+            .get("/x", ok("x"))
+            .put("/x", ok("x"))
+            .delete("/x", ok("x"))
+            .get("/x", ok("replaced"))
+            .post("/y", ok("y"));
+
+        let response = router.handle(&request("POST", "/x")).unwrap();
+
+        assert_eq!(response.header("Allow"), Some("GET, PUT, DELETE"));
+        assert_eq!(
+            router.handle(&request("GET", "/x")).unwrap().body(),
+            b"replaced"
+        );
+    }
+
+    #[test]
+    fn router_answers_head_with_get_handler() {
+        let router = Router::new().get("/x", ok("get x"));
+
+        let response = router.handle(&request("HEAD", "/x")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.body(), b"get x");
+    }
+
+    #[test]
+    fn router_returns_not_implemented_for_unhandled_method() {
+        let router = Router::new().get("/x", ok("x")).post("/y", ok("y"));
+
+        let response = router
+            .handle(&request("CONNECT", "example.com:443"))
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NotImplemented);
+        assert!(response.header("Allow").is_none());
+    }
+
+    #[test]
+    fn router_answers_options_asterisk_with_every_method() {
+        let router = Router::new()
+            .get("/x", ok("x"))
+            .post("/x", ok("x"))
+            .get("/y", ok("y"))
+            .delete("/y", ok("y"));
+
+        let response = router.handle(&request("OPTIONS", "*")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.header("Allow"), Some("GET, POST, DELETE, OPTIONS"));
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn router_answers_options_for_path_with_its_methods() {
+        let router = Router::new()
+            .get("/x", ok("x"))
+            .put("/x", ok("x"))
+            .post("/y", ok("y"));
+
+        let response = router.handle(&request("OPTIONS", "/x")).unwrap();
+
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.header("Allow"), Some("GET, PUT, OPTIONS"));
+        assert!(response.body().is_empty());
+        let unknown = router.handle(&request("OPTIONS", "/z")).unwrap();
+        assert_eq!(unknown.status(), StatusCode::NotFound);
+    }
+}
@@ -0,0 +1,2019 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, BufWriter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, error, info, warn};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    http::{
+        proxy::ProxyHeader,
+        reader::{Limits, RequestReader},
+        Method, Request, RequestParsingError, Response, StatusCode, Version, DEFAULT_MAX_BODY_SIZE,
+    },
+};
+
+mod client_limit;
+mod connection;
+mod env;
+mod rate_limit;
+mod request_id;
+mod testing;
+mod tunnel;
+
+use client_limit::ClientConnections;
+pub use connection::Connection;
+pub use env::ConfigError;
+pub use rate_limit::RateLimit;
+use rate_limit::{RateLimiter, MAX_RETRY_AFTER};
+pub use request_id::{RequestId, REQUEST_ID_HEADER};
+pub use testing::TestServer;
+pub use tunnel::TunnelConfig;
+
+pub const DEFAULT_PORT: u16 = 8080;
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DEFAULT_MAX_REQUESTS_PER_CONNECTION: usize = 100;
+pub const DEFAULT_SERVER_NAME: &str = concat!("simple_http/", env!("CARGO_PKG_VERSION"));
+
+/// What the server does with a new connection while [`ServerConfig::max_connections`] are
+/// already being handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Saturation {
+    /// Hold the connection until another one completes. Later clients queue in the listen
+    /// backlog meanwhile.
+    Wait,
+    /// Answer with `503 Service Unavailable` and close the connection.
+    Reject,
+}
+
+/// Callback notified of every request the server rejects, see [`ServerConfig::on_rejected`].
+#[derive(Clone)]
+pub struct RejectionHook(Arc<RejectionFn>);
+
+type RejectionFn = dyn Fn(&RequestParsingError, Option<SocketAddr>) + Send + Sync;
+
+impl RejectionHook {
+    pub fn new(
+        hook: impl Fn(&RequestParsingError, Option<SocketAddr>) + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for RejectionHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RejectionHook")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address the listener binds to. Both IPv4 and IPv6 addresses are accepted, and a port of 0
+    /// lets the OS pick a free one.
+    pub addr: SocketAddr,
+    /// How long in-flight connections are given to complete once shutdown is requested.
+    pub shutdown_timeout: Duration,
+    /// Largest request body accepted, in bytes. Larger requests are rejected with 413.
+    pub max_body_size: u64,
+    /// Total time allowed to read one request, from its start line to the end of its body.
+    /// Requests taking longer are answered with 408, so slow clients can't hold a connection.
+    /// `None` disables the limit.
+    pub request_timeout: Option<Duration>,
+    /// How long a connection may wait for the first byte of its next request before it is
+    /// closed. `None` keeps idle connections open indefinitely.
+    pub idle_timeout: Option<Duration>,
+    /// Requests answered on one keep-alive connection before it is closed, so a client
+    /// pipelining requests can't hold a worker forever. `None` disables the limit.
+    pub max_requests_per_connection: Option<usize>,
+    /// Limits on the request head, such as the number of headers.
+    pub limits: Limits,
+    /// Whether `TRACE` requests are answered by the server itself, reflecting the request back
+    /// (see [`Response::trace`]), instead of being passed to the handler.
+    pub echo_trace: bool,
+    /// Whether connections start with a PROXY protocol v1 line, whose source address then
+    /// replaces the peer address. Only enable this behind a load balancer that always sends it,
+    /// as clients could otherwise spoof their address.
+    pub proxy_protocol: bool,
+    /// Most connections handled at once, `None` for no limit.
+    pub max_connections: Option<usize>,
+    /// Behavior for new connections once `max_connections` is reached.
+    pub saturation: Saturation,
+    /// Most connections a single client address may have open at once, so one client can't
+    /// hold every worker with slow connections. Further connections are answered with
+    /// `429 Too Many Requests` and closed. `None` for no limit.
+    pub max_connections_per_ip: Option<usize>,
+    /// Value of the `Server` header added to responses that don't set one. `None` omits it.
+    pub server_name: Option<String>,
+    /// Called with the parsing error and client address whenever a request is rejected, such as
+    /// for an unsupported version or a timeout, to feed metrics or alerting. The rejection is
+    /// logged either way.
+    pub on_rejected: Option<RejectionHook>,
+    /// Requests allowed per client address. Requests past it are answered with
+    /// `429 Too Many Requests` and a `Retry-After` header, without reaching the handler. `None`
+    /// disables rate limiting.
+    pub rate_limit: Option<RateLimit>,
+    /// Destinations `CONNECT` requests may open a tunnel to, the server then relaying bytes
+    /// between the client and the destination. `None`, the default, passes `CONNECT` requests
+    /// to the handler like others: an open proxy would let anyone reach any host through it.
+    pub tunnel: Option<TunnelConfig>,
+    /// Whether each request gets a [`RequestId`], stored in its extensions, logged in its access
+    /// log line and echoed in an `X-Request-Id` response header. A client-sent `X-Request-Id` is
+    /// reused, so the ID set by a proxy in front of the server carries through.
+    pub request_ids: bool,
+    /// Time source for request deadlines and rate limiting, the real clock by default. Tests can
+    /// use a [`ManualClock`](crate::clock::ManualClock) to time out requests without waiting.
+    pub clock: Arc<dyn Clock>,
+    /// Methods the server accepts, such as to turn off `TRACE` or `CONNECT` everywhere. Requests
+    /// with another method are answered with `405 Method Not Allowed` and an `Allow` header
+    /// listing these, before reaching the handler. `None`, the default, accepts every method.
+    pub allowed_methods: Option<Vec<Method>>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: (Ipv4Addr::UNSPECIFIED, DEFAULT_PORT).into(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            max_requests_per_connection: Some(DEFAULT_MAX_REQUESTS_PER_CONNECTION),
+            limits: Limits::default(),
+            echo_trace: true,
+            proxy_protocol: false,
+            max_connections: None,
+            max_connections_per_ip: None,
+            saturation: Saturation::Wait,
+            server_name: Some(DEFAULT_SERVER_NAME.to_owned()),
+            on_rejected: None,
+            rate_limit: None,
+            tunnel: None,
+            request_ids: false,
+            clock: Arc::new(SystemClock),
+            allowed_methods: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn with_addr(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            ..Default::default()
+        }
+    }
+}
+
+/// Produces a response for each request received by a [`Server`]. A failed request is answered
+/// with an error response built from the [`HandlerError`].
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, request: &Request) -> Result<Response, HandlerError>;
+}
+
+impl<F, O> Handler for F
+where
+    F: Fn(&Request) -> O + Send + Sync + 'static,
+    O: Into<HandlerResult>,
+{
+    fn handle(&self, request: &Request) -> Result<Response, HandlerError> {
+        self(request).into().0
+    }
+}
+
+/// What a closure [`Handler`] may return: a [`Response`], or a `Result` of one.
+pub struct HandlerResult(Result<Response, HandlerError>);
+
+impl From<Response> for HandlerResult {
+    fn from(value: Response) -> Self {
+        Self(Ok(value))
+    }
+}
+
+impl<E: Into<HandlerError>> From<Result<Response, E>> for HandlerResult {
+    fn from(value: Result<Response, E>) -> Self {
+        Self(value.map_err(Into::into))
+    }
+}
+
+/// Why a [`Handler`] failed, answered with its status code, `500 Internal Server Error` by
+/// default. The underlying error is logged, not sent to the client.
+#[derive(Debug)]
+pub struct HandlerError {
+    status: StatusCode,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl HandlerError {
+    pub fn new(source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::with_status(StatusCode::InternalServerError, source)
+    }
+
+    pub fn with_status(
+        status: StatusCode,
+        source: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            status,
+            source: source.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn source(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.source
+    }
+}
+
+impl Display for HandlerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.source, self.status)
+    }
+}
+
+// Not implementing `Error` lets any error convert with `?`, which would otherwise conflict with
+// the blanket `From<T> for T`
+impl<E: Error + Send + Sync + 'static> From<E> for HandlerError {
+    fn from(value: E) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Handle used to stop a running [`Server`] from another thread.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    shutdown: Arc<AtomicBool>,
+    draining: Arc<AtomicBool>,
+    addr: ListenAddr,
+}
+
+impl ShutdownHandle {
+    /// Stops the server from accepting new connections. In-flight connections are given up to
+    /// [`ServerConfig::shutdown_timeout`] to complete before [`Server::serve`] returns.
+    pub fn shutdown(&self) {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            // Already shutting down
+            return;
+        }
+
+        // The accept loop is blocked in `accept()`, wake it up with a dummy connection so it can
+        // notice the flag
+        let woken = match &self.addr {
+            ListenAddr::Tcp(addr) => {
+                let mut addr = *addr;
+                if addr.ip().is_unspecified() {
+                    let loopback: IpAddr = match addr.ip() {
+                        IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                        IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+                    };
+                    addr.set_ip(loopback);
+                }
+                TcpStream::connect(addr).map(drop)
+            }
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => UnixStream::connect(path).map(drop),
+        };
+        if let Err(e) = woken {
+            warn!("Failed to wake up the accept loop: {}", e);
+        }
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Shuts down like [`ShutdownHandle::shutdown`], and also closes keep-alive connections: each
+    /// one is closed after answering its current or next request, with `Connection: close`.
+    pub fn begin_drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+        self.shutdown();
+    }
+}
+
+/// Longest pause between accept attempts while the process is out of resources.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How the accept loop reacts to a failed `accept`.
+#[derive(Debug, PartialEq, Eq)]
+enum AcceptFailure {
+    /// Only that connection was lost, such as a client resetting it before it was accepted.
+    Transient,
+    /// Out of file descriptors or memory: retrying immediately would fail again, so the loop
+    /// waits for connections to close and free resources.
+    Exhausted,
+    /// The listener itself is unusable.
+    Fatal,
+}
+
+impl AcceptFailure {
+    fn of(e: &io::Error) -> Self {
+        // EMFILE and ENFILE have the same values on Linux, macOS and the BSDs
+        const EMFILE: i32 = 24;
+        const ENFILE: i32 = 23;
+        match e.kind() {
+            io::ErrorKind::InvalidInput => Self::Fatal,
+            io::ErrorKind::OutOfMemory => Self::Exhausted,
+            _ if cfg!(unix) && matches!(e.raw_os_error(), Some(EMFILE | ENFILE)) => Self::Exhausted,
+            _ => Self::Transient,
+        }
+    }
+}
+
+/// Number of connections currently being handled.
+#[derive(Default)]
+struct InFlight {
+    count: Mutex<usize>,
+    done: Condvar,
+}
+
+impl InFlight {
+    fn enter(self: &Arc<Self>) -> InFlightGuard {
+        *self.count.lock().unwrap() += 1;
+        InFlightGuard(Arc::clone(self))
+    }
+
+    fn count(&self) -> usize {
+        *self.count.lock().unwrap()
+    }
+
+    /// Waits until fewer than `max` connections are running. Returns `false` without waiting
+    /// further if `shutdown` gets set.
+    fn wait_below(&self, max: usize, shutdown: &AtomicBool) -> bool {
+        let mut count = self.count.lock().unwrap();
+        while *count >= max {
+            if shutdown.load(Ordering::SeqCst) {
+                return false;
+            }
+            // Periodically wake up to check the shutdown flag
+            count = self
+                .done
+                .wait_timeout(count, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+        true
+    }
+
+    /// Waits for every connection to complete, returning the number still running if `timeout`
+    /// elapsed first.
+    fn wait(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            count = self.done.wait_timeout(count, remaining).unwrap().0;
+        }
+        *count
+    }
+}
+
+struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.done.notify_all();
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// Where a [`Listener`] accepts connections.
+#[derive(Debug, Clone)]
+enum ListenAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+pub struct Server {
+    listener: Listener,
+    config: ServerConfig,
+    shutdown: Arc<AtomicBool>,
+    /// Set while draining, keep-alive connections are closed after their next response.
+    draining: Arc<AtomicBool>,
+}
+
+impl Server {
+    pub fn bind(config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(config.addr)?;
+        Ok(Self {
+            listener: Listener::Tcp(listener),
+            config,
+            shutdown: Arc::default(),
+            draining: Arc::default(),
+        })
+    }
+
+    /// Listens on a Unix domain socket at `path` instead of [`ServerConfig::addr`], for example
+    /// behind a reverse proxy on the same host. Fails if `path` already exists.
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl AsRef<Path>, config: ServerConfig) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let listener = UnixListener::bind(&path)?;
+        Ok(Self {
+            listener: Listener::Unix(listener, path),
+            config,
+            shutdown: Arc::default(),
+            draining: Arc::default(),
+        })
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Address the listener is actually bound to, which differs from the configured one when
+    /// binding to port 0. Errors for a server listening on a Unix socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr(),
+            #[cfg(unix)]
+            Listener::Unix(..) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "listening on a Unix socket",
+            )),
+        }
+    }
+
+    fn listen_addr(&self) -> io::Result<ListenAddr> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr().map(ListenAddr::Tcp),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Ok(ListenAddr::Unix(path.clone())),
+        }
+    }
+
+    pub fn shutdown_handle(&self) -> io::Result<ShutdownHandle> {
+        Ok(ShutdownHandle {
+            shutdown: Arc::clone(&self.shutdown),
+            draining: Arc::clone(&self.draining),
+            addr: self.listen_addr()?,
+        })
+    }
+
+    /// Stops accepting connections and closes keep-alive ones once they answered their current
+    /// request, see [`ShutdownHandle::begin_drain`].
+    pub fn begin_drain(&self) -> io::Result<()> {
+        self.shutdown_handle()?.begin_drain();
+        Ok(())
+    }
+
+    /// Accepts connections until shutdown is requested, handling each one on its own thread.
+    /// Errors are logged and only affect their own connection. Failed accepts are retried, backing
+    /// off while out of file descriptors, unless the listener itself is unusable.
+    pub fn serve<H: Handler>(&self, handler: H) -> io::Result<()> {
+        info!("Listening on {}", self.listen_addr()?);
+
+        let handler = Arc::new(handler);
+        match &self.listener {
+            Listener::Tcp(listener) => self.accept_loop(listener.incoming(), handler),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => self.accept_loop(listener.incoming(), handler),
+        }
+    }
+
+    fn accept_loop<C: Connection, H: Handler>(
+        &self,
+        incoming: impl Iterator<Item = io::Result<C>>,
+        handler: Arc<H>,
+    ) -> io::Result<()> {
+        let config = Arc::new(self.config.clone());
+        let in_flight = Arc::new(InFlight::default());
+        let limiter = self
+            .config
+            .rate_limit
+            .map(RateLimiter::new)
+            .transpose()?
+            .map(Arc::new);
+        let clients = self
+            .config
+            .max_connections_per_ip
+            .map(|max| Arc::new(ClientConnections::new(max)));
+        let mut backoff = Duration::ZERO;
+        let mut fatal = None;
+
+        for stream in incoming {
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let mut stream = match stream {
+                Ok(stream) => {
+                    backoff = Duration::ZERO;
+                    stream
+                }
+                Err(e) => match AcceptFailure::of(&e) {
+                    AcceptFailure::Transient => {
+                        // Only that connection is lost, keep serving the others
+                        error!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                    AcceptFailure::Exhausted => {
+                        backoff = (backoff * 2).clamp(Duration::from_millis(5), MAX_ACCEPT_BACKOFF);
+                        error!(
+                            "Failed to accept connection, retrying in {:?}: {}",
+                            backoff, e
+                        );
+                        thread::sleep(backoff);
+                        continue;
+                    }
+                    AcceptFailure::Fatal => {
+                        error!("Stopping, the listener failed: {}", e);
+                        fatal = Some(e);
+                        break;
+                    }
+                },
+            };
+
+            if let Some(max) = self.config.max_connections {
+                match self.config.saturation {
+                    Saturation::Wait => {
+                        if !in_flight.wait_below(max, &self.shutdown) {
+                            break;
+                        }
+                    }
+                    Saturation::Reject if in_flight.count() >= max => {
+                        warn!("Rejecting connection, {} already in flight", max);
+                        if let Err(e) =
+                            reject_connection(&mut stream, StatusCode::ServiceUnavailable)
+                        {
+                            debug!("Failed to send 503: {}", e);
+                        }
+                        continue;
+                    }
+                    Saturation::Reject => {}
+                }
+            }
+
+            let handler = Arc::clone(&handler);
+            let config = Arc::clone(&config);
+            let draining = Arc::clone(&self.draining);
+            let limiter = limiter.clone();
+            let clients = clients.clone();
+            let guard = in_flight.enter();
+            thread::spawn(move || {
+                let _guard = guard;
+                let result = handle_connection(
+                    stream,
+                    &*handler,
+                    &config,
+                    &draining,
+                    limiter.as_deref(),
+                    clients.as_deref(),
+                );
+                if let Err(e) = result {
+                    error!("Error handling connection: {}", e);
+                }
+            });
+        }
+
+        info!("Shutting down, waiting for in-flight connections");
+        let remaining = in_flight.wait(self.config.shutdown_timeout);
+        if remaining > 0 {
+            warn!(
+                "Shutdown timeout elapsed with {} connection(s) still in flight",
+                remaining
+            );
+        }
+        fatal.map_or(Ok(()), Err)
+    }
+}
+
+fn handle_connection<C: Connection, H: Handler>(
+    stream: C,
+    handler: &H,
+    config: &ServerConfig,
+    draining: &AtomicBool,
+    limiter: Option<&RateLimiter>,
+    clients: Option<&ClientConnections>,
+) -> io::Result<()> {
+    let mut peer_addr = stream.peer_addr();
+    let describe = |addr: Option<SocketAddr>| {
+        addr.map_or_else(|| "a local socket".to_owned(), |addr| addr.to_string())
+    };
+    debug!("Received a new connection from {}", describe(peer_addr));
+
+    // A single reader is kept for the whole connection: bytes buffered past the end of one
+    // request are the start of the next one when the client pipelines requests.
+    let mut reader = RequestReader::from_reader(stream.try_clone()?);
+    reader.set_limits(config.limits);
+    reader.set_clock(Arc::clone(&config.clock));
+    let mut writer = stream;
+
+    if config.proxy_protocol {
+        writer.set_read_timeout(config.request_timeout)?;
+        match ProxyHeader::read_from(&mut reader) {
+            Ok(ProxyHeader::Tcp { source, .. }) => {
+                debug!("{} is proxying {}", describe(peer_addr), source);
+                peer_addr = Some(source);
+            }
+            Ok(ProxyHeader::Unknown) => {}
+            Err(e) => {
+                // Not answered, the client isn't the expected balancer
+                warn!("Closing connection from {}: {}", describe(peer_addr), e);
+                return Ok(());
+            }
+        }
+    }
+    let peer = describe(peer_addr);
+
+    // Declared after the writer so it is dropped first: once the client sees the connection
+    // close, it is no longer counted
+    let _client_guard = match clients.zip(peer_addr) {
+        Some((clients, addr)) => match clients.enter(addr.ip()) {
+            Some(guard) => Some(guard),
+            None => {
+                warn!("Rejecting connection, {} has too many open", peer);
+                return reject_connection(&mut writer, StatusCode::TooManyRequests);
+            }
+        },
+        None => None,
+    };
+
+    let mut answered = 0;
+    loop {
+        writer.set_read_timeout(config.idle_timeout)?;
+        match reader.fill_buf() {
+            Ok(0) => {
+                debug!("Connection from {} closed by the client", peer);
+                return Ok(());
+            }
+            Ok(_) => {
+                // Garbage, such as a TLS handshake, is refused before buffering a whole line
+                if let Some(&byte) = reader.peek().first().filter(|&&b| !can_start_request(b)) {
+                    let e = RequestParsingError::NotHttp(byte);
+                    warn!("Rejecting connection from {}: {}", peer, e);
+                    if let Some(hook) = &config.on_rejected {
+                        (hook.0)(&e, peer_addr);
+                    }
+                    return reject_connection(&mut writer, e.status_code());
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                debug!("Closing idle connection from {}", peer);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+        // Each read also gives up after the full timeout, so a silent client can't block past it
+        writer.set_read_timeout(config.request_timeout)?;
+
+        let mut request_line = None;
+        let mut request_id = None;
+        let mut is_head = false;
+        let mut version = None;
+        let read = read_request(&mut reader, &mut writer, config, &mut version);
+        let (mut response, keep_alive) = match read {
+            Ok(mut request) => {
+                if let Some(addr) = peer_addr {
+                    request.set_peer_addr(addr);
+                }
+                if config.request_ids {
+                    let id = RequestId::for_request(&request);
+                    request_id = Some(id.clone());
+                    request.extensions_mut().insert(id);
+                }
+                debug!("{:?}", request);
+                is_head = request.method() == Method::Head;
+                request_line = Some(format!(
+                    "{} {} {}",
+                    request.method(),
+                    request.target(),
+                    request.version()
+                ));
+                let retry_after = limiter
+                    .zip(request.peer_addr())
+                    .and_then(|(limiter, addr)| limiter.check(addr.ip(), config.clock.now()).err());
+                let response = if let Some(retry_after) = retry_after {
+                    warn!("Rate limiting {}", peer);
+                    error_response(StatusCode::TooManyRequests)
+                        .with_version(request.version())
+                        .with_header(
+                            "Retry-After",
+                            (retry_after.min(MAX_RETRY_AFTER).as_secs_f64().ceil() as u64)
+                                .to_string(),
+                        )
+                } else if let Some(allowed) = config
+                    .allowed_methods
+                    .as_ref()
+                    .filter(|allowed| !allowed.contains(&request.method()))
+                {
+                    let allow: Vec<_> = allowed.iter().map(ToString::to_string).collect();
+                    error_response(StatusCode::MethodNotAllowed)
+                        .with_version(request.version())
+                        .with_header("Allow", allow.join(", "))
+                } else if let Some(tunnel) = config
+                    .tunnel
+                    .as_ref()
+                    .filter(|_| request.method() == Method::Connect)
+                {
+                    match open_tunnel(request.target(), tunnel, config.request_timeout) {
+                        Ok(upstream) => {
+                            // No Content-Length, the tunnel starts right after the head
+                            write!(
+                                writer,
+                                "{} 200 Connection Established\r\n",
+                                request.version()
+                            )?;
+                            if let Some(id) = &request_id {
+                                write!(writer, "{}: {}\r\n", REQUEST_ID_HEADER, id)?;
+                            }
+                            writer.write_all(b"\r\n")?;
+                            writer.flush()?;
+                            info!(
+                                "{}",
+                                access_log_line(
+                                    peer_addr.map(|addr| addr.ip()),
+                                    request_id.as_ref(),
+                                    request_line.as_deref(),
+                                    StatusCode::Ok
+                                )
+                            );
+                            return tunnel::relay(reader, writer, upstream, config.idle_timeout);
+                        }
+                        Err(status) => error_response(status),
+                    }
+                } else if config.echo_trace && request.method() == Method::Trace {
+                    Response::trace(&request)
+                } else {
+                    handler.handle(&request).unwrap_or_else(|e| {
+                        error!("Handler failed for a request from {}: {}", peer, e);
+                        error_response(e.status())
+                    })
+                };
+                (response, request.is_keep_alive())
+            }
+            Err(RequestParsingError::ConnectionClosed) => {
+                debug!("Connection from {} closed by the client", peer);
+                return Ok(());
+            }
+            Err(e) if e.io_error_kind() == Some(io::ErrorKind::UnexpectedEof) => {
+                warn!("Connection from {} closed in the middle of a request", peer);
+                return Ok(());
+            }
+            Err(RequestParsingError::Io(e)) => return Err(e),
+            Err(e) => {
+                // The rest of a rejected request can't be trusted, so the connection is closed
+                warn!("Rejecting request from {}: {}", peer, e);
+                if config.request_ids {
+                    request_id = Some(RequestId::generate());
+                }
+                if let Some(hook) = &config.on_rejected {
+                    (hook.0)(&e, peer_addr);
+                }
+                // Versions that couldn't be parsed are answered as HTTP/1.1
+                let mut response =
+                    error_response(e.status_code()).with_version(version.unwrap_or(Version::V1_1));
+                if let Some(versions) = e.supported_versions() {
+                    let versions: Vec<_> = versions.iter().map(ToString::to_string).collect();
+                    response = response.with_body(format!(
+                        "{}\nSupported versions: {}",
+                        e.status_code(),
+                        versions.join(", ")
+                    ));
+                }
+                (response, false)
+            }
+        };
+
+        answered += 1;
+        let exhausted = config
+            .max_requests_per_connection
+            .is_some_and(|max| answered >= max);
+        // A body without length runs until the connection closes
+        let keep_alive = keep_alive
+            && !exhausted
+            && !draining.load(Ordering::SeqCst)
+            && !response.is_close_delimited();
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        response.set_header("Connection", connection);
+        if let Some(id) = &request_id {
+            response.set_header(REQUEST_ID_HEADER, id.as_str());
+        }
+        if let Some(name) = &config.server_name {
+            if response.header("Server").is_none() {
+                response.set_header("Server", name.as_str());
+            }
+        }
+        // Buffered so the head isn't sent in one write per line, the response flushes it before
+        // the next request is read
+        let mut buffered = BufWriter::new(&mut writer);
+        if is_head {
+            // Handlers answer HEAD like GET, the body is only left out here
+            response.write_head_to(&mut buffered)?;
+        } else {
+            response.write_to(&mut buffered)?;
+        }
+        drop(buffered);
+        info!(
+            "{}",
+            access_log_line(
+                peer_addr.map(|addr| addr.ip()),
+                request_id.as_ref(),
+                request_line.as_deref(),
+                response.status()
+            )
+        );
+
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// Connects to the `host:port` target of a `CONNECT` request, or gives the status to answer
+/// with if it isn't allowed or can't be reached. Each address of the target gets `timeout` to
+/// accept the connection, so an unresponsive destination can't hold the client indefinitely.
+fn open_tunnel(
+    target: &str,
+    tunnel: &TunnelConfig,
+    timeout: Option<Duration>,
+) -> Result<TcpStream, StatusCode> {
+    if !tunnel.is_allowed(target) {
+        warn!("Refusing to open a tunnel to {}", target);
+        return Err(StatusCode::Forbidden);
+    }
+
+    let connect = |addr| match timeout {
+        Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+        None => TcpStream::connect(addr),
+    };
+    let mut error = io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to");
+    match target.to_socket_addrs() {
+        Ok(addrs) => {
+            for addr in addrs {
+                match connect(addr) {
+                    Ok(stream) => return Ok(stream),
+                    Err(e) => error = e,
+                }
+            }
+        }
+        Err(e) => error = e,
+    }
+
+    warn!("Failed to open a tunnel to {}: {}", target, error);
+    match error.kind() {
+        io::ErrorKind::TimedOut => Err(StatusCode::GatewayTimeout),
+        _ => Err(StatusCode::BadGateway),
+    }
+}
+
+/// Whether a request may start with `byte`: the first letter of a method, which are all
+/// uppercase, or the empty lines or byte order mark tolerated before the request line.
+fn can_start_request(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || matches!(byte, b'\r' | b'\n' | 0xef)
+}
+
+/// Answers a connection the server won't handle with `status`, without reading its request.
+fn reject_connection<C: Connection>(stream: &mut C, status: StatusCode) -> io::Result<()> {
+    error_response(status)
+        .with_header("Connection", "close")
+        .write_to(stream)?;
+
+    // Closing with unread data makes the OS reset the connection, which can discard the response
+    // before the client reads it. Briefly drain what the client already sent.
+    stream.set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut buf = [0; 1024];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads a full request, answering `Expect: 100-continue` before reading the body.
+fn read_request<C: Connection>(
+    reader: &mut RequestReader<C>,
+    writer: &mut C,
+    config: &ServerConfig,
+    version: &mut Option<Version>,
+) -> Result<Request, RequestParsingError> {
+    reader.set_deadline(
+        config
+            .request_timeout
+            .map(|timeout| config.clock.now() + timeout),
+    );
+    let mut request = Request::read_head_noting_version(reader, version)?;
+
+    if request.expects_continue() {
+        let too_large = request
+            .content_length()?
+            .is_some_and(|length| length > config.max_body_size);
+        if too_large {
+            // Reject before the client sends the body
+            return Err(RequestParsingError::ExpectationFailed {
+                limit: config.max_body_size,
+            });
+        }
+
+        Response::new(StatusCode::Continue)
+            .write_to(writer)
+            .map_err(RequestParsingError::Io)?;
+    }
+
+    request.read_body(reader, config.max_body_size)?;
+    Ok(request)
+}
+
+/// One access log line, e.g. `127.0.0.1 5f2b9c0e8a1d4f37 GET /path HTTP/1.1 -> 200`. A missing
+/// peer address (Unix sockets), request ID (when disabled) or request line (requests that
+/// couldn't be parsed) is logged as `-`.
+fn access_log_line(
+    peer: Option<IpAddr>,
+    request_id: Option<&RequestId>,
+    request_line: Option<&str>,
+    status: StatusCode,
+) -> String {
+    format!(
+        "{} {} {} -> {}",
+        peer.map_or_else(|| "-".to_owned(), |ip| ip.to_string()),
+        request_id.map_or("-", RequestId::as_str),
+        request_line.unwrap_or("-"),
+        status.code()
+    )
+}
+
+fn error_response(status: StatusCode) -> Response {
+    Response::new(status)
+        .with_header("Content-Type", "text/plain")
+        .with_body(status.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        sync::mpsc,
+    };
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn local_server(config: ServerConfig) -> Server {
+        let config = ServerConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            ..config
+        };
+        Server::bind(config).expect("error binding server")
+    }
+
+    /// Starts serving on a background thread, returning the address to connect to.
+    fn spawn_server<H: Handler>(config: ServerConfig, handler: H) -> (SocketAddr, ShutdownHandle) {
+        let server = local_server(config);
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle().unwrap();
+        thread::spawn(move || server.serve(handler));
+        (addr, handle)
+    }
+
+    fn echo(request: &Request) -> Response {
+        Response::new(StatusCode::Ok).with_body(request.body_bytes().unwrap_or_default())
+    }
+
+    /// Reads from `stream` until the end of a response head.
+    fn read_head(stream: &mut TcpStream) -> String {
+        let mut head = Vec::new();
+        let mut byte = [0];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream
+                .read_exact(&mut byte)
+                .expect("error reading response head");
+            head.push(byte[0]);
+        }
+        String::from_utf8(head).unwrap()
+    }
+
+    /// Sends `request` on a new connection and reads the response until the server closes it.
+    fn send(addr: SocketAddr, request: &str) -> String {
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn access_log_line_formats_request() {
+        let line = access_log_line(
+            Some(Ipv4Addr::LOCALHOST.into()),
+            None,
+            Some("GET /path HTTP/1.1"),
+            StatusCode::Ok,
+        );
+
+        assert_eq!(line, "127.0.0.1 - GET /path HTTP/1.1 -> 200");
+    }
+
+    #[test]
+    fn access_log_line_formats_rejected_request() {
+        let line = access_log_line(
+            Some(Ipv4Addr::LOCALHOST.into()),
+            None,
+            None,
+            StatusCode::BadRequest,
+        );
+
+        assert_eq!(line, "127.0.0.1 - - -> 400");
+    }
+
+    #[test]
+    fn access_log_line_formats_missing_peer() {
+        let line = access_log_line(None, None, Some("GET / HTTP/1.1"), StatusCode::Ok);
+
+        assert_eq!(line, "- - GET / HTTP/1.1 -> 200");
+    }
+
+    #[test]
+    fn access_log_line_formats_request_id() {
+        let id = RequestId::generate();
+        let line = access_log_line(None, Some(&id), Some("GET / HTTP/1.1"), StatusCode::Ok);
+
+        assert_eq!(line, format!("- {} GET / HTTP/1.1 -> 200", id));
+    }
+
+    #[test]
+    fn server_echoes_request_id_seen_by_handler() {
+        let config = ServerConfig {
+            request_ids: true,
+            ..Default::default()
+        };
+        let server = TestServer::with_config(
+            |request: &Request| {
+                let id = request.extensions().get::<RequestId>().unwrap();
+                Response::new(StatusCode::Ok).with_body(id.to_string())
+            },
+            config,
+        );
+
+        let read = |output: Vec<u8>| {
+            crate::http::client::read_response(&mut RequestReader::from_reader(&output[..]))
+                .expect("error reading response")
+        };
+        let response = read(server.send("GET / HTTP/1.1\r\nHost: a\r\n\r\n").unwrap());
+        let id = std::str::from_utf8(response.body()).unwrap();
+        assert_eq!(id.len(), 16);
+        assert_eq!(response.header(REQUEST_ID_HEADER), Some(id));
+
+        let request = "GET / HTTP/1.1\r\nHost: a\r\nX-Request-Id: from-proxy\r\n\r\n";
+        let response = read(server.send(request).unwrap());
+        assert_eq!(response.body(), b"from-proxy");
+        assert_eq!(response.header(REQUEST_ID_HEADER), Some("from-proxy"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn server_responds_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("simple_http-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server =
+            Server::bind_unix(&path, ServerConfig::default()).expect("error binding server");
+        let handle = server.shutdown_handle().unwrap();
+        let serving = thread::spawn(move || server.serve(echo));
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 2\r\n\r\nhi")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        handle.shutdown();
+        serving.join().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nhi"));
+    }
+
+    #[test]
+    fn default_config_binds_all_interfaces_on_8080() {
+        let config = ServerConfig::default();
+
+        assert_eq!(config.addr, "0.0.0.0:8080".parse().unwrap());
+    }
+
+    #[test]
+    fn config_accepts_ipv6_addr() {
+        let config = ServerConfig::with_addr("[::1]:8080".parse().unwrap());
+
+        assert_eq!(config.addr.ip(), Ipv6Addr::LOCALHOST);
+        assert_eq!(config.addr.port(), 8080);
+    }
+
+    #[test]
+    fn server_binds_to_configured_addr() {
+        let server = local_server(ServerConfig::default());
+        let addr = server.local_addr().expect("error reading local address");
+
+        assert_eq!(addr.ip(), Ipv4Addr::LOCALHOST);
+        assert_ne!(addr.port(), 0);
+    }
+
+    #[test]
+    fn server_responds_to_request() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(
+            addr,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 5\r\n\r\nhello",
+        );
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn server_keeps_http_1_1_connection_alive() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        for body in ["first", "second"] {
+            let request = format!(
+                "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            client.write_all(request.as_bytes()).unwrap();
+
+            let head = read_head(&mut client);
+            assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(head.contains("\r\nConnection: keep-alive\r\n"));
+            let mut response_body = vec![0; body.len()];
+            client.read_exact(&mut response_body).unwrap();
+            assert_eq!(response_body, body.as_bytes());
+        }
+    }
+
+    #[test]
+    fn server_answers_pipelined_requests_in_order() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(
+            addr,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nfirst\
+             POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 6\r\n\r\nsecond\
+             GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+
+        let statuses = response.matches("HTTP/1.1 200 OK\r\n").count();
+        assert_eq!(statuses, 3);
+        let first = response
+            .find("\r\n\r\nfirst")
+            .expect("missing first response");
+        let second = response
+            .find("\r\n\r\nsecond")
+            .expect("missing second response");
+        assert!(first < second);
+    }
+
+    #[test]
+    fn server_closes_http_1_0_connection() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        // Reading to the end only succeeds if the server closes the connection
+        let response = send(addr, "GET / HTTP/1.0\r\nHost: localhost\r\n\r\n");
+
+        assert!(response.contains("\r\nConnection: close\r\n"));
+    }
+
+    #[test]
+    fn server_closes_connection_when_requested() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(
+            addr,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+
+        assert!(response.contains("\r\nConnection: close\r\n"));
+    }
+
+    #[test]
+    fn server_rejects_malformed_request() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(addr, "GeT / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn server_rejects_body_too_large() {
+        let config = ServerConfig {
+            max_body_size: 4,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let response = send(
+            addr,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+        );
+
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    }
+
+    #[test]
+    fn server_times_out_slow_request() {
+        let config = ServerConfig {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        // The head is never completed, the server has to give up on its own
+        let response = send(addr, "GET / HTTP/1.1\r\nHost: local");
+
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    }
+
+    #[test]
+    fn server_times_out_request_past_deadline_without_waiting() {
+        /// Moves a minute forward every time it is read
+        #[derive(Debug)]
+        struct Racing(ManualClock);
+
+        impl Clock for Racing {
+            fn now(&self) -> Instant {
+                self.0.advance(Duration::from_secs(60));
+                self.0.now()
+            }
+        }
+
+        let config = ServerConfig {
+            request_timeout: Some(Duration::from_secs(30)),
+            clock: Arc::new(Racing(ManualClock::new())),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        // The body is read past the deadline set when the request started
+        let request = "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello";
+        let output = String::from_utf8(server.send(request).unwrap()).unwrap();
+
+        assert!(
+            output.starts_with("HTTP/1.1 408 Request Timeout\r\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn server_rejects_too_many_headers() {
+        let config = ServerConfig {
+            limits: Limits {
+                max_headers: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let response = send(addr, "GET / HTTP/1.1\r\nHost: a\r\nB: 1\r\nC: 2\r\n\r\n");
+
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+    }
+
+    #[test]
+    fn server_lists_supported_versions_when_rejecting_version() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(addr, "GET / HTTP/3\r\nHost: a\r\n\r\n");
+
+        assert!(response.starts_with("HTTP/1.1 505 HTTP Version Not Supported\r\n"));
+        assert!(response.ends_with("\nSupported versions: HTTP/1.0, HTTP/1.1"));
+    }
+
+    #[test]
+    fn server_adds_date_and_server_headers() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(
+            addr,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+
+        assert!(response.contains(&format!("\r\nServer: {}\r\n", DEFAULT_SERVER_NAME)));
+        let date = response
+            .split("\r\n")
+            .find_map(|line| line.strip_prefix("Date: "))
+            .expect("missing Date header");
+        assert!(date.ends_with(" GMT"));
+        assert_eq!(date.len(), "Sun, 06 Nov 1994 08:49:37 GMT".len());
+    }
+
+    #[test]
+    fn server_ignores_connection_closed_without_request() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        // Like a health check, connect and close straight away
+        drop(TcpStream::connect(addr).unwrap());
+
+        // The server keeps serving afterwards
+        let response = send(
+            addr,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn server_uses_proxy_protocol_source_address() {
+        let config = ServerConfig {
+            proxy_protocol: true,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, |request: &Request| {
+            Response::new(StatusCode::Ok).with_body(request.peer_addr().unwrap().to_string())
+        });
+
+        let response = send(
+            addr,
+            "PROXY TCP4 203.0.113.7 10.0.0.1 51000 80\r\n\
+             GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+
+        assert!(response.ends_with("\r\n\r\n203.0.113.7:51000"));
+    }
+
+    #[test]
+    fn server_closes_connection_without_proxy_header() {
+        let config = ServerConfig {
+            proxy_protocol: true,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let response = send(addr, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn server_rejects_connections_over_limit() {
+        let config = ServerConfig {
+            max_connections: Some(1),
+            saturation: Saturation::Reject,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        // Held open without sending anything, taking the only slot
+        let mut first = TcpStream::connect(addr).unwrap();
+        let mut second = TcpStream::connect(addr).unwrap();
+        let mut response = String::new();
+        second.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+
+        // The first connection is still served normally
+        first
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        assert!(read_head(&mut first).starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn server_queues_connections_over_limit() {
+        let config = ServerConfig {
+            max_connections: Some(1),
+            saturation: Saturation::Wait,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+        let request = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(request).unwrap();
+        assert!(read_head(&mut first).starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let mut second = TcpStream::connect(addr).unwrap();
+        second.write_all(request).unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let mut byte = [0];
+        let e = second
+            .read(&mut byte)
+            .expect_err("second connection was served");
+        assert!(matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+
+        // Closing the first connection frees the slot for the second one
+        drop(first);
+        second.set_read_timeout(None).unwrap();
+        assert!(read_head(&mut second).starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn server_echoes_trace_requests() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let response = send(
+            addr,
+            "TRACE / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\nContent-Type: message/http\r\n"));
+        assert!(
+            response.ends_with("TRACE / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        );
+    }
+
+    #[test]
+    fn server_closes_idle_connection() {
+        let config = ServerConfig {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let head = read_head(&mut client);
+        assert!(head.contains("\r\nConnection: keep-alive\r\n"));
+
+        // Nothing else is sent, so the server ends the connection once the idle timeout passes
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn server_rejects_body_cut_short() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nhello")
+            .unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn server_rejects_head_cut_short() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: example.com")
+            .unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn server_sends_continue_before_reading_body() {
+        let (addr, _handle) = spawn_server(ServerConfig::default(), echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+            .unwrap();
+        assert_eq!(read_head(&mut client), "HTTP/1.1 100 Continue\r\n\r\n");
+
+        client.write_all(b"hello").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn server_rejects_continue_when_body_too_large() {
+        let config = ServerConfig {
+            max_body_size: 4,
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+            .unwrap();
+
+        assert!(read_head(&mut client).starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+    }
+
+    #[test]
+    fn server_never_sends_continue_for_oversized_body() {
+        let config = ServerConfig {
+            max_body_size: 4,
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        let output = server
+            .send("POST / HTTP/1.1\r\nHost: a\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\nhello")
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+        assert!(!output.contains("100 Continue"));
+        // The body that was sent anyway isn't read as a next request
+        assert_eq!(output.matches("HTTP/1.1 ").count(), 1);
+        assert!(output.contains("\r\nConnection: close\r\n"));
+    }
+
+    #[test]
+    fn shutdown_stops_idle_server() {
+        let server = local_server(ServerConfig::default());
+        let handle = server
+            .shutdown_handle()
+            .expect("error creating shutdown handle");
+
+        let serving = thread::spawn(move || server.serve(echo));
+        handle.shutdown();
+
+        serving
+            .join()
+            .unwrap()
+            .expect("error returned by stopped server");
+        assert!(handle.is_shutdown());
+    }
+
+    #[test]
+    fn shutdown_waits_for_in_flight_connections() {
+        let server = local_server(ServerConfig::default());
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle().unwrap();
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let finished = Arc::new(AtomicBool::new(false));
+        let handler_finished = Arc::clone(&finished);
+        let serving = thread::spawn(move || {
+            server.serve(move |_: &Request| {
+                started_tx.send(()).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                handler_finished.store(true, Ordering::SeqCst);
+                Response::new(StatusCode::Ok).with_body("done")
+            })
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        // Only shut down once the connection is being handled
+        started_rx.recv().unwrap();
+        handle.shutdown();
+        serving.join().unwrap().unwrap();
+
+        // The server only returns once the handler has finished
+        assert!(finished.load(Ordering::SeqCst));
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.ends_with("\r\n\r\ndone"));
+    }
+
+    #[test]
+    fn shutdown_gives_up_after_timeout() {
+        let config = ServerConfig {
+            shutdown_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let server = local_server(config);
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle().unwrap();
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let serving = thread::spawn(move || {
+            server.serve(move |_: &Request| {
+                started_tx.send(()).unwrap();
+                thread::sleep(Duration::from_secs(5));
+                Response::new(StatusCode::Ok)
+            })
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        started_rx.recv().unwrap();
+        let start = Instant::now();
+        handle.shutdown();
+        serving.join().unwrap().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn accept_loop_survives_transient_accept_errors() {
+        let server = local_server(ServerConfig::default());
+        let emfile = io::Error::from_raw_os_error(24);
+        assert_eq!(AcceptFailure::of(&emfile), AcceptFailure::Exhausted);
+
+        let incoming: Vec<io::Result<TcpStream>> = vec![
+            Err(io::ErrorKind::ConnectionAborted.into()),
+            Err(emfile),
+            Err(io::ErrorKind::ConnectionReset.into()),
+            Err(io::ErrorKind::InvalidInput.into()),
+            Err(io::ErrorKind::ConnectionAborted.into()),
+        ];
+        let mut remaining = incoming.into_iter();
+
+        let e = server
+            .accept_loop(remaining.by_ref(), Arc::new(echo))
+            .expect_err("expected the fatal error");
+
+        // Only the fatal error stopped the loop
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(remaining.count(), 1);
+    }
+
+    #[test]
+    fn server_answers_handler_errors_with_their_status() {
+        let server = TestServer::new(|request: &Request| match request.target() {
+            "/missing" => Err(HandlerError::with_status(
+                StatusCode::NotFound,
+                "no such item",
+            )),
+            _ => {
+                let length: u64 = "not a number".parse()?;
+                Ok(Response::new(StatusCode::Ok).with_body(length.to_string()))
+            }
+        });
+
+        let response = server
+            .send("GET /missing HTTP/1.1\r\nHost: a\r\n\r\nGET /other HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let response = String::from_utf8(response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        // The cause is only logged
+        assert!(response.contains("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(!response.contains("invalid digit"));
+    }
+
+    #[test]
+    fn drain_closes_keep_alive_connections_after_next_response() {
+        let (addr, handle) = spawn_server(ServerConfig::default(), echo);
+        let mut client = TcpStream::connect(addr).unwrap();
+        let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        client.write_all(request.as_bytes()).unwrap();
+        assert!(read_head(&mut client).contains("\r\nConnection: keep-alive\r\n"));
+
+        handle.begin_drain();
+        assert!(handle.is_shutdown());
+        client.write_all(request.as_bytes()).unwrap();
+        assert!(read_head(&mut client).contains("\r\nConnection: close\r\n"));
+        // The server closes the connection after that response
+        assert_eq!(client.read(&mut [0; 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn server_answers_head_without_body() {
+        let server =
+            TestServer::new(|_: &Request| Response::new(StatusCode::Ok).with_body("hello"));
+
+        let output = server
+            .send("HEAD / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        // The HEAD response announces the body but the next response follows its head directly
+        let (head, get) = output.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("\r\nContent-Length: 5"));
+        assert!(get.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(get.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn server_notifies_rejection_hook() {
+        let rejected = Arc::new(Mutex::new(Vec::new()));
+        let config = ServerConfig {
+            on_rejected: Some(RejectionHook::new({
+                let rejected = Arc::clone(&rejected);
+                move |e, _| rejected.lock().unwrap().push(e.status_code())
+            })),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        server.send("GET / HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        server.send("GET / HTTP/3\r\nHost: a\r\n\r\n").unwrap();
+
+        assert_eq!(
+            *rejected.lock().unwrap(),
+            [StatusCode::HttpVersionNotSupported]
+        );
+    }
+
+    #[test]
+    fn server_closes_connection_after_body_without_length() {
+        let server = TestServer::new(|_: &Request| {
+            Response::new(StatusCode::Ok)
+                .with_header("Transfer-Encoding", "gzip")
+                .with_body("compressed")
+        });
+
+        let output = server
+            .send("GET / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("\r\nConnection: close\r\n"));
+        assert!(!output.contains("Content-Length"));
+        // The pipelined request isn't answered, the closed connection ends the first body
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(output.ends_with("\r\n\r\ncompressed"));
+    }
+
+    #[test]
+    fn server_answers_errors_with_request_version() {
+        let config = ServerConfig {
+            max_body_size: 4,
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        for (request, status_line) in [
+            (
+                "POST / HTTP/1.0\r\nContent-Length: 10\r\n\r\n",
+                "HTTP/1.0 413 Payload Too Large\r\n",
+            ),
+            (
+                "GET / HTTP/1.0\r\nBad Header: x\r\n\r\n",
+                "HTTP/1.0 400 Bad Request\r\n",
+            ),
+            (
+                "GET / HTTP/3\r\nHost: a\r\n\r\n",
+                "HTTP/1.1 505 HTTP Version Not Supported\r\n",
+            ),
+        ] {
+            let output = String::from_utf8(server.send(request).unwrap()).unwrap();
+
+            assert!(output.starts_with(status_line), "{}", output);
+        }
+    }
+
+    #[test]
+    fn server_closes_connection_after_max_requests() {
+        let config = ServerConfig {
+            max_requests_per_connection: Some(2),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        let request = "GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let output = server.send(request.repeat(3)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 2);
+        assert_eq!(output.matches("\r\nConnection: keep-alive\r\n").count(), 1);
+        assert_eq!(output.matches("\r\nConnection: close\r\n").count(), 1);
+    }
+
+    #[test]
+    fn server_rate_limits_clients() {
+        let config = ServerConfig {
+            rate_limit: Some(RateLimit {
+                requests_per_second: 0.1,
+                burst: 2,
+            }),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+        let request = "GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n";
+
+        assert!(send(addr, request).starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(send(addr, request).starts_with("HTTP/1.1 200 OK\r\n"));
+        let response = send(addr, request);
+        assert!(response.starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+        assert!(response.contains("\r\nRetry-After: 10\r\n"));
+    }
+
+    #[test]
+    fn server_caps_retry_after() {
+        let config = ServerConfig {
+            rate_limit: Some(RateLimit {
+                requests_per_second: 1e-300,
+                burst: 1,
+            }),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+        let request = "GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n";
+
+        assert!(send(addr, request).starts_with("HTTP/1.1 200 OK\r\n"));
+        let response = send(addr, request);
+        assert!(response.contains(&format!(
+            "\r\nRetry-After: {}\r\n",
+            MAX_RETRY_AFTER.as_secs()
+        )));
+    }
+
+    #[test]
+    fn server_refuses_invalid_rate_limit() {
+        let server = local_server(ServerConfig {
+            rate_limit: Some(RateLimit {
+                requests_per_second: f64::NAN,
+                burst: 1,
+            }),
+            ..Default::default()
+        });
+
+        let err = server.serve(echo).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn server_rejects_tls_handshake_without_waiting() {
+        let config = ServerConfig {
+            request_timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        // Start of a TLS ClientHello: no line ending, the client then waits for the server
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(&[
+                0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xfc, 0x03, 0x03,
+            ])
+            .unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let started = Instant::now();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn requests_may_start_with_method_or_empty_line() {
+        for byte in [b'G', b'P', b'\r', b'\n', 0xef] {
+            assert!(can_start_request(byte), "{:#04x}", byte);
+        }
+        for byte in [0x16, 0x00, b'g', b' ', b'{'] {
+            assert!(!can_start_request(byte), "{:#04x}", byte);
+        }
+    }
+
+    #[test]
+    fn server_refuses_methods_not_allowed() {
+        let config = ServerConfig {
+            allowed_methods: Some(vec![Method::Get, Method::Head, Method::Post]),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let response = send(
+            addr,
+            "TRACE / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("\r\nAllow: GET, HEAD, POST\r\n"));
+
+        let response = send(
+            addr,
+            "GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn server_limits_connections_per_client() {
+        let config = ServerConfig {
+            max_connections_per_ip: Some(1),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first
+            .write_all(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        assert!(read_head(&mut first).starts_with("HTTP/1.1 200 OK\r\n"));
+
+        // The first connection is still open
+        let request = "GET / HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n";
+        assert!(send(addr, request).starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+
+        // Once it is closed, the client may connect again
+        first.write_all(request.as_bytes()).unwrap();
+        first.read_to_string(&mut String::new()).unwrap();
+        assert!(send(addr, request).starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn server_tunnels_connect_to_allowed_destination() {
+        // An upstream echoing one message back
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut buf = [0; 5];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+        let config = ServerConfig {
+            tunnel: Some(TunnelConfig {
+                allowed: vec![upstream_addr.clone()],
+            }),
+            ..Default::default()
+        };
+        let (addr, _handle) = spawn_server(config, echo);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        write!(
+            client,
+            "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\nhello",
+            upstream_addr
+        )
+        .unwrap();
+        let head = read_head(&mut client);
+        assert_eq!(head, "HTTP/1.1 200 Connection Established\r\n\r\n");
+
+        let mut echoed = String::new();
+        client.read_to_string(&mut echoed).unwrap();
+        assert_eq!(echoed, "hello");
+    }
+
+    #[test]
+    fn server_refuses_tunnel_to_other_destinations() {
+        let config = ServerConfig {
+            tunnel: Some(TunnelConfig {
+                allowed: vec!["example.com:443".to_owned()],
+            }),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(echo, config);
+
+        let request = "CONNECT internal:22 HTTP/1.1\r\nHost: internal:22\r\n\r\n";
+        let output = String::from_utf8(server.send(request).unwrap()).unwrap();
+
+        assert!(
+            output.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+            "{}",
+            output
+        );
+    }
+}
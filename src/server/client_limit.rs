@@ -0,0 +1,101 @@
+//! Limit on the connections a single client address may have open at once.
+
+use std::{collections::HashMap, net::IpAddr, sync::Mutex};
+
+/// Open connections of every client address, shared by the server's connections.
+#[derive(Debug)]
+pub(crate) struct ClientConnections {
+    max: usize,
+    counts: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl ClientConnections {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            counts: Mutex::default(),
+        }
+    }
+
+    /// Counts a new connection from `client`, or returns `None` if it already has the maximum
+    /// open. The connection stays counted until the guard is dropped.
+    pub(crate) fn enter(&self, client: IpAddr) -> Option<ClientGuard<'_>> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(client).or_insert(0);
+        if *count >= self.max {
+            if *count == 0 {
+                // Only with a maximum of 0, don't keep an entry for a client without connections
+                counts.remove(&client);
+            }
+            return None;
+        }
+        *count += 1;
+        Some(ClientGuard {
+            connections: self,
+            client,
+        })
+    }
+
+    #[cfg(test)]
+    fn count(&self, client: IpAddr) -> usize {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&client)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// A connection counted by [`ClientConnections::enter`], uncounted on drop, whether the
+/// connection ended normally or with an error.
+pub(crate) struct ClientGuard<'a> {
+    connections: &'a ClientConnections,
+    client: IpAddr,
+}
+
+impl Drop for ClientGuard<'_> {
+    fn drop(&mut self) {
+        let mut counts = self.connections.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                // Clients that went away aren't tracked forever
+                counts.remove(&self.client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_connections_limit_each_client_separately() {
+        let connections = ClientConnections::new(2);
+        let a: IpAddr = "192.0.2.1".parse().unwrap();
+        let b: IpAddr = "192.0.2.2".parse().unwrap();
+
+        let first = connections.enter(a).unwrap();
+        let _second = connections.enter(a).unwrap();
+        assert!(connections.enter(a).is_none());
+        assert!(connections.enter(b).is_some());
+
+        drop(first);
+        assert_eq!(connections.count(a), 1);
+        let _third = connections.enter(a).unwrap();
+        assert!(connections.enter(a).is_none());
+    }
+
+    #[test]
+    fn client_connections_forget_clients_without_connections() {
+        let connections = ClientConnections::new(1);
+        let a: IpAddr = "192.0.2.1".parse().unwrap();
+
+        drop(connections.enter(a).unwrap());
+
+        assert!(connections.counts.lock().unwrap().is_empty());
+        assert!(ClientConnections::new(0).enter(a).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! [`ServerConfig`] from environment variables, for deployments configured without code.
+
+use std::{env, error::Error, fmt::Display, str::FromStr, time::Duration};
+
+use super::ServerConfig;
+
+/// An environment variable read by [`ServerConfig::from_env`] whose value couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub var: &'static str,
+    pub value: String,
+    /// What the value should have been, ie: `a number of milliseconds`.
+    pub expected: &'static str,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid value {:?} for {}, expected {}",
+            self.value, self.var, self.expected
+        )
+    }
+}
+
+impl Error for ConfigError {}
+
+impl ServerConfig {
+    /// Reads the config from environment variables, using the default of each setting whose
+    /// variable is unset or empty:
+    ///
+    /// - `SIMPLE_HTTP_ADDR`: address to bind, ie: `0.0.0.0:8080` or `[::1]:3000`
+    /// - `SIMPLE_HTTP_MAX_CONNECTIONS`: most connections handled at once, `0` for no limit
+    /// - `SIMPLE_HTTP_MAX_CONNECTIONS_PER_IP`: most connections open at once from one client
+    ///   address, `0` for no limit
+    /// - `SIMPLE_HTTP_MAX_BODY_SIZE`: largest request body accepted, in bytes
+    /// - `SIMPLE_HTTP_MAX_HEADERS`: most header lines accepted in a request
+    /// - `SIMPLE_HTTP_MAX_START_LINE`: longest request line accepted, in bytes
+    /// - `SIMPLE_HTTP_MAX_HEADER_LINE`: longest header line accepted, in bytes
+    /// - `SIMPLE_HTTP_REQUEST_TIMEOUT_MS`: time allowed to read a request, `0` for no limit
+    /// - `SIMPLE_HTTP_IDLE_TIMEOUT_MS`: time a keep-alive connection may stay idle, `0` for no
+    ///   limit
+    /// - `SIMPLE_HTTP_SHUTDOWN_TIMEOUT_MS`: time given to in-flight connections on shutdown
+    ///
+    /// Errors on the first variable whose value can't be parsed, rather than ignoring it.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let vars = Vars(lookup);
+        let mut config = Self::default();
+
+        if let Some(addr) = vars.parse("SIMPLE_HTTP_ADDR", "an address such as 0.0.0.0:8080")? {
+            config.addr = addr;
+        }
+        if let Some(max) = vars.parse("SIMPLE_HTTP_MAX_CONNECTIONS", "a number of connections")? {
+            config.max_connections = (max > 0).then_some(max);
+        }
+        if let Some(max) = vars.parse(
+            "SIMPLE_HTTP_MAX_CONNECTIONS_PER_IP",
+            "a number of connections",
+        )? {
+            config.max_connections_per_ip = (max > 0).then_some(max);
+        }
+        if let Some(size) = vars.parse("SIMPLE_HTTP_MAX_BODY_SIZE", "a number of bytes")? {
+            config.max_body_size = size;
+        }
+        if let Some(max) = vars.parse("SIMPLE_HTTP_MAX_HEADERS", "a number of headers")? {
+            config.limits.max_headers = max;
+        }
+        if let Some(max) = vars.parse("SIMPLE_HTTP_MAX_START_LINE", "a number of bytes")? {
+            config.limits.max_start_line = max;
+        }
+        if let Some(max) = vars.parse("SIMPLE_HTTP_MAX_HEADER_LINE", "a number of bytes")? {
+            config.limits.max_header_line = max;
+        }
+        if let Some(timeout) = vars.millis("SIMPLE_HTTP_REQUEST_TIMEOUT_MS")? {
+            config.request_timeout = (!timeout.is_zero()).then_some(timeout);
+        }
+        if let Some(timeout) = vars.millis("SIMPLE_HTTP_IDLE_TIMEOUT_MS")? {
+            config.idle_timeout = (!timeout.is_zero()).then_some(timeout);
+        }
+        if let Some(timeout) = vars.millis("SIMPLE_HTTP_SHUTDOWN_TIMEOUT_MS")? {
+            config.shutdown_timeout = timeout;
+        }
+        Ok(config)
+    }
+}
+
+struct Vars<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Vars<F> {
+    /// Value of `var` parsed as `T`, `None` if it is unset or empty.
+    fn parse<T: FromStr>(
+        &self,
+        var: &'static str,
+        expected: &'static str,
+    ) -> Result<Option<T>, ConfigError> {
+        let Some(value) = (self.0)(var).filter(|value| !value.trim().is_empty()) else {
+            return Ok(None);
+        };
+        value.trim().parse().map(Some).map_err(|_| ConfigError {
+            var,
+            value,
+            expected,
+        })
+    }
+
+    fn millis(&self, var: &'static str) -> Result<Option<Duration>, ConfigError> {
+        Ok(self
+            .parse(var, "a number of milliseconds")?
+            .map(Duration::from_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{Ipv6Addr, SocketAddr},
+    };
+
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> Result<ServerConfig, ConfigError> {
+        let vars: HashMap<_, _> = vars
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        ServerConfig::from_vars(|var| vars.get(var).cloned())
+    }
+
+    #[test]
+    fn from_env_defaults_when_unset() {
+        let config = config(&[("SIMPLE_HTTP_ADDR", "")]).unwrap();
+        let default = ServerConfig::default();
+
+        assert_eq!(config.addr, default.addr);
+        assert_eq!(config.max_connections, default.max_connections);
+        assert_eq!(config.request_timeout, default.request_timeout);
+        assert_eq!(config.limits, default.limits);
+    }
+
+    #[test]
+    fn from_env_reads_every_setting() {
+        let config = config(&[
+            ("SIMPLE_HTTP_ADDR", "[::1]:3000"),
+            ("SIMPLE_HTTP_MAX_CONNECTIONS", "64"),
+            ("SIMPLE_HTTP_MAX_CONNECTIONS_PER_IP", "8"),
+            ("SIMPLE_HTTP_MAX_BODY_SIZE", "1024"),
+            ("SIMPLE_HTTP_MAX_HEADERS", "20"),
+            ("SIMPLE_HTTP_MAX_START_LINE", " 512 "),
+            ("SIMPLE_HTTP_MAX_HEADER_LINE", "256"),
+            ("SIMPLE_HTTP_REQUEST_TIMEOUT_MS", "1500"),
+            ("SIMPLE_HTTP_IDLE_TIMEOUT_MS", "0"),
+            ("SIMPLE_HTTP_SHUTDOWN_TIMEOUT_MS", "250"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.addr, SocketAddr::from((Ipv6Addr::LOCALHOST, 3000)));
+        assert_eq!(config.max_connections, Some(64));
+        assert_eq!(config.max_connections_per_ip, Some(8));
+        assert_eq!(config.max_body_size, 1024);
+        assert_eq!(config.limits.max_headers, 20);
+        assert_eq!(config.limits.max_start_line, 512);
+        assert_eq!(config.limits.max_header_line, 256);
+        assert_eq!(config.request_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(config.idle_timeout, None);
+        assert_eq!(config.shutdown_timeout, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn from_env_errors_on_malformed_value() {
+        let e = config(&[("SIMPLE_HTTP_REQUEST_TIMEOUT_MS", "5s")]).expect_err("expected error");
+        assert_eq!(e.var, "SIMPLE_HTTP_REQUEST_TIMEOUT_MS");
+        assert_eq!(
+            e.to_string(),
+            "invalid value \"5s\" for SIMPLE_HTTP_REQUEST_TIMEOUT_MS, expected a number of milliseconds"
+        );
+
+        let e = config(&[("SIMPLE_HTTP_ADDR", "localhost")]).expect_err("expected error");
+        assert_eq!(e.var, "SIMPLE_HTTP_ADDR");
+        let e = config(&[("SIMPLE_HTTP_MAX_HEADERS", "-1")]).expect_err("expected error");
+        assert_eq!(e.var, "SIMPLE_HTTP_MAX_HEADERS");
+    }
+}
@@ -0,0 +1,185 @@
+//! Per-client request rate limiting with token buckets.
+
+use std::{
+    collections::HashMap,
+    io,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Most buckets kept. Once reached, full buckets are dropped, which doesn't change any client's
+/// allowance since a full bucket behaves like a missing one. If that isn't enough, such as
+/// during a flood from many addresses, the least recently updated buckets go as well.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Buckets evicted at once when the limit is reached, so the map is only scanned once per this
+/// many new clients rather than on each of them.
+const EVICTED_AT_ONCE: usize = MAX_TRACKED_CLIENTS / 10;
+
+/// Longest wait sent in `Retry-After`, however long until the client gets a token back.
+pub(crate) const MAX_RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Rate allowed to each client address, see [`ServerConfig::rate_limit`](super::ServerConfig).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Sustained rate, at which a client's allowance refills.
+    pub requests_per_second: f64,
+    /// Requests a client can send at once after being idle.
+    pub burst: u32,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets for every client seen, shared by the server's connections.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Errors with [`io::ErrorKind::InvalidInput`] unless the rate is finite and positive.
+    pub(crate) fn new(limit: RateLimit) -> io::Result<Self> {
+        let rate = limit.requests_per_second;
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "rate limit must be a positive number of requests, got {}",
+                    rate
+                ),
+            ));
+        }
+        Ok(Self {
+            limit,
+            buckets: Mutex::default(),
+        })
+    }
+
+    /// Takes a token from `client`'s bucket, or returns how long until one is available.
+    pub(crate) fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(self.limit.burst);
+        let rate = self.limit.requests_per_second;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            evict(&mut buckets, now, rate, burst);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            // A tiny rate can take longer than a `Duration` holds
+            Err(Duration::try_from_secs_f64((1.0 - bucket.tokens) / rate).unwrap_or(Duration::MAX))
+        }
+    }
+}
+
+/// Makes room for [`EVICTED_AT_ONCE`] new buckets, dropping full ones first, then the least
+/// recently updated.
+fn evict(buckets: &mut HashMap<IpAddr, Bucket>, now: Instant, rate: f64, burst: f64) {
+    buckets.retain(|_, bucket| {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens + elapsed * rate < burst
+    });
+    let target = MAX_TRACKED_CLIENTS - EVICTED_AT_ONCE;
+    if buckets.len() <= target {
+        return;
+    }
+
+    let mut updated: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+    let excess = buckets.len() - target;
+    let (_, &mut cutoff, _) = updated.select_nth_unstable(excess - 1);
+    buckets.retain(|_, bucket| bucket.updated > cutoff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_limits_each_client_separately() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 2.0,
+            burst: 3,
+        })
+        .unwrap();
+        let now = Instant::now();
+        let a: IpAddr = "192.0.2.1".parse().unwrap();
+        let b: IpAddr = "192.0.2.2".parse().unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check(a, now), Ok(()));
+        }
+        assert_eq!(limiter.check(a, now), Err(Duration::from_millis(500)));
+        assert_eq!(limiter.check(b, now), Ok(()));
+
+        // Refilled at the sustained rate
+        assert_eq!(limiter.check(a, now + Duration::from_millis(500)), Ok(()));
+        assert!(limiter.check(a, now + Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn rate_limiter_stays_bounded_under_flood() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 1.0,
+            burst: 2,
+        })
+        .unwrap();
+        let start = Instant::now();
+        // Every client keeps a partly used bucket, so none can be dropped for being full
+        for i in 0..(MAX_TRACKED_CLIENTS as u32 + 5) {
+            let client = IpAddr::from(std::net::Ipv4Addr::from(0x0a00_0000 + i));
+            let now = start + Duration::from_micros(u64::from(i));
+            assert_eq!(limiter.check(client, now), Ok(()));
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_TRACKED_CLIENTS, "{}", buckets.len());
+        // The oldest clients were evicted, the latest kept
+        assert!(!buckets.contains_key(&"10.0.0.0".parse().unwrap()));
+        assert!(buckets.contains_key(&IpAddr::from(std::net::Ipv4Addr::from(
+            0x0a00_0000 + MAX_TRACKED_CLIENTS as u32 + 4
+        ))));
+    }
+
+    #[test]
+    fn rate_limiter_rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let limit = RateLimit {
+                requests_per_second: rate,
+                burst: 1,
+            };
+            let err = RateLimiter::new(limit).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn rate_limiter_caps_wait_for_tiny_rates() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 1e-300,
+            burst: 1,
+        })
+        .unwrap();
+        let now = Instant::now();
+        let client = "192.0.2.1".parse().unwrap();
+
+        assert_eq!(limiter.check(client, now), Ok(()));
+        assert_eq!(limiter.check(client, now), Err(Duration::MAX));
+    }
+}
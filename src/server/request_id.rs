@@ -0,0 +1,88 @@
+//! Unique request IDs, for following one request across the logs of the services it goes
+//! through.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Display,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::http::Request;
+
+/// Header carrying the request ID, both in requests and in responses.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest client-sent ID reused, longer ones are replaced by a generated one.
+const MAX_CLIENT_ID_LEN: usize = 128;
+
+/// ID of a request, stored in its [`extensions`](Request::extensions) when
+/// [`ServerConfig::request_ids`](super::ServerConfig) is enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// The `X-Request-Id` the client sent, such as one set by a proxy in front of the server, or
+    /// a new ID if it sent none or one that isn't printable ASCII.
+    pub(crate) fn for_request(request: &Request) -> Self {
+        request
+            .header(REQUEST_ID_HEADER)
+            .filter(|id| is_valid(id))
+            .map_or_else(Self::generate, |id| Self(id.to_owned()))
+    }
+
+    /// A random 16 hex digit ID.
+    pub(crate) fn generate() -> Self {
+        // Each `RandomState` is randomly keyed, the counter tells apart IDs made with equal keys
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        Self(format!("{:016x}", hasher.finish()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Whether a client-sent ID can be reused as is, in logs and in the response header.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_CLIENT_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    #[test]
+    fn generated_ids_are_unique_hex() {
+        let first = RequestId::generate();
+        let second = RequestId::generate();
+
+        assert_eq!(first.as_str().len(), 16);
+        assert!(first.as_str().bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn for_request_reuses_valid_client_id() {
+        let request = Request::builder(Method::Get, "/")
+            .with_header(REQUEST_ID_HEADER, "abc-123")
+            .build();
+        assert_eq!(RequestId::for_request(&request).as_str(), "abc-123");
+
+        for invalid in ["", "has space", &"a".repeat(MAX_CLIENT_ID_LEN + 1)] {
+            let request = Request::builder(Method::Get, "/")
+                .with_header(REQUEST_ID_HEADER, invalid)
+                .build();
+            assert_eq!(RequestId::for_request(&request).as_str().len(), 16);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Driving the server's connection handling over in-memory streams, to test handlers end to end
+//! without binding a port.
+
+use std::{
+    io::{self, Cursor, Read, Write},
+    net::SocketAddr,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
+
+use super::{
+    client_limit::ClientConnections, handle_connection, Connection, Handler, RateLimiter,
+    ServerConfig,
+};
+
+/// A connection whose client side is written up front: reads return the given bytes then EOF,
+/// and writes are collected for inspection.
+#[derive(Debug, Clone)]
+struct MemoryStream {
+    input: Arc<Mutex<Cursor<Vec<u8>>>>,
+    output: Arc<Mutex<Vec<u8>>>,
+    peer: Option<SocketAddr>,
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.lock().unwrap().read(buf)
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Connection for MemoryStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.clone())
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        // Reads never block, the client has already sent everything
+        Ok(())
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+}
+
+/// Runs a handler behind the same connection handling as a [`Server`](super::Server), but over
+/// in-memory connections. Each call to [`TestServer::send`] is a new connection.
+pub struct TestServer<H: Handler> {
+    handler: H,
+    config: ServerConfig,
+    limiter: Option<RateLimiter>,
+    clients: Option<ClientConnections>,
+}
+
+impl<H: Handler> TestServer<H> {
+    pub fn new(handler: H) -> Self {
+        Self::with_config(handler, ServerConfig::default())
+    }
+
+    /// The config's address and `max_connections` are unused, there is no listener. Rate limits
+    /// and per client limits apply to connections opened with [`TestServer::send_from`], the
+    /// others have no peer address.
+    ///
+    /// # Panics
+    ///
+    /// If the config's rate limit isn't a positive rate, which [`Server::serve`](super::Server)
+    /// would reject.
+    pub fn with_config(handler: H, config: ServerConfig) -> Self {
+        let limiter = config
+            .rate_limit
+            .map(|limit| RateLimiter::new(limit).expect("invalid rate limit"));
+        let clients = config.max_connections_per_ip.map(ClientConnections::new);
+        Self {
+            handler,
+            config,
+            limiter,
+            clients,
+        }
+    }
+
+    /// Opens a connection, sends `input` and closes the client's side, returning everything the
+    /// server wrote before it closed the connection. Pipelined requests are all answered.
+    pub fn send(&self, input: impl Into<Vec<u8>>) -> io::Result<Vec<u8>> {
+        self.connect(None, input.into())
+    }
+
+    /// Like [`TestServer::send`], from a client at `peer`.
+    pub fn send_from(&self, peer: SocketAddr, input: impl Into<Vec<u8>>) -> io::Result<Vec<u8>> {
+        self.connect(Some(peer), input.into())
+    }
+
+    fn connect(&self, peer: Option<SocketAddr>, input: Vec<u8>) -> io::Result<Vec<u8>> {
+        let stream = MemoryStream {
+            input: Arc::new(Mutex::new(Cursor::new(input))),
+            output: Arc::new(Mutex::new(Vec::new())),
+            peer,
+        };
+        let output = Arc::clone(&stream.output);
+
+        handle_connection(
+            stream,
+            &self.handler,
+            &self.config,
+            &AtomicBool::new(false),
+            self.limiter.as_ref(),
+            self.clients.as_ref(),
+        )?;
+        let output = std::mem::take(&mut *output.lock().unwrap());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{Request, Response, StatusCode},
+        server::RateLimit,
+    };
+
+    #[test]
+    fn test_server_answers_without_network() {
+        let server = TestServer::new(|request: &Request| {
+            Response::new(StatusCode::Ok).with_body(request.target().to_owned())
+        });
+
+        let output = server
+            .send("GET /a HTTP/1.1\r\nHost: localhost\r\n\r\nGET /b HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let responses: Vec<_> = output.split("HTTP/1.1 200 OK\r\n").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].ends_with("\r\n\r\n/a"));
+        assert!(responses[1].ends_with("\r\n\r\n/b"));
+    }
+
+    #[test]
+    fn test_server_limits_clients_by_peer_address() {
+        let config = ServerConfig {
+            rate_limit: Some(RateLimit {
+                requests_per_second: 0.1,
+                burst: 1,
+            }),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(|_: &Request| Response::ok_with("ok"), config);
+        let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let a: SocketAddr = "192.0.2.1:1234".parse().unwrap();
+        let b: SocketAddr = "192.0.2.2:1234".parse().unwrap();
+
+        assert!(server
+            .send_from(a, request)
+            .unwrap()
+            .starts_with(b"HTTP/1.1 200 OK\r\n"));
+        let limited = server.send_from(a, request).unwrap();
+        assert!(limited.starts_with(b"HTTP/1.1 429 Too Many Requests\r\n"));
+        assert!(server
+            .send_from(b, request)
+            .unwrap()
+            .starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(server
+            .send(request)
+            .unwrap()
+            .starts_with(b"HTTP/1.1 200 OK\r\n"));
+
+        let config = ServerConfig {
+            max_connections_per_ip: Some(0),
+            ..Default::default()
+        };
+        let server = TestServer::with_config(|_: &Request| Response::ok_with("ok"), config);
+        let rejected = server.send_from(a, request).unwrap();
+        assert!(rejected.starts_with(b"HTTP/1.1 429 Too Many Requests\r\n"));
+    }
+}
@@ -0,0 +1,166 @@
+//! `CONNECT` tunneling (RFC 7231 §4.3.6), relaying bytes between a client and the destination it
+//! asked for, as a forward proxy does.
+
+use std::{
+    io::{self, Read, Write},
+    thread,
+    time::Duration,
+};
+
+use super::Connection;
+use crate::http::reader::RequestReader;
+
+/// Destinations `CONNECT` requests may open a tunnel to, see
+/// [`ServerConfig::tunnel`](super::ServerConfig).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TunnelConfig {
+    /// Allowed `host:port` destinations, such as `example.com:443`, compared ignoring case.
+    /// Other destinations are answered with `403 Forbidden`.
+    pub allowed: Vec<String>,
+}
+
+impl TunnelConfig {
+    pub(crate) fn is_allowed(&self, authority: &str) -> bool {
+        self.allowed
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(authority))
+    }
+}
+
+/// Copies bytes both ways between the client and `upstream` until either side closes or stays
+/// silent for `idle_timeout`, then closes both. Bytes the client already sent after its request
+/// are forwarded first.
+pub(crate) fn relay<C: Connection, U: Connection>(
+    reader: RequestReader<C>,
+    client: C,
+    upstream: U,
+    idle_timeout: Option<Duration>,
+) -> io::Result<()> {
+    let (mut client_read, mut buffered) = reader.into_inner();
+    client_read.set_read_timeout(idle_timeout)?;
+    upstream.set_read_timeout(idle_timeout)?;
+    let mut upstream_write = upstream.try_clone()?;
+    let mut upstream_read = upstream;
+    let mut client_write = client;
+
+    thread::scope(|scope| {
+        let uploading = scope.spawn(move || {
+            let result = upstream_write
+                .write_all(buffered.make_contiguous())
+                .and_then(|_| io::copy(&mut client_read, &mut upstream_write));
+            // The client is done, which also ends the download below
+            let _ = upstream_write.shutdown();
+            result
+        });
+
+        let downloaded = copy_flushing(&mut upstream_read, &mut client_write);
+        let _ = client_write.shutdown();
+        let uploaded = uploading.join().expect("tunnel upload panicked");
+        downloaded.and(uploaded).map(|_| ())
+    })
+}
+
+/// Like [`io::copy`], but flushing after each read so the client gets responses as they arrive.
+fn copy_flushing<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<u64> {
+    let mut buf = [0; 8 * 1024];
+    let mut copied = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        writer.flush()?;
+        copied += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    /// One end of a connection that sends `input` then ends, collecting what is written to it.
+    #[derive(Clone)]
+    struct Pipe {
+        input: Arc<Mutex<Cursor<Vec<u8>>>>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Pipe {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: Arc::new(Mutex::new(Cursor::new(input.to_vec()))),
+                output: Arc::default(),
+            }
+        }
+
+        fn output(&self) -> Vec<u8> {
+            self.output.lock().unwrap().clone()
+        }
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.lock().unwrap().read(buf)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Connection for Pipe {
+        fn try_clone(&self) -> io::Result<Self> {
+            Ok(self.clone())
+        }
+
+        fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn peer_addr(&self) -> Option<SocketAddr> {
+            None
+        }
+    }
+
+    #[test]
+    fn relay_copies_bytes_both_ways() {
+        let client =
+            Pipe::new(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com\r\n\r\nhello");
+        let upstream = Pipe::new(b"welcome");
+        let mut reader = RequestReader::from_reader(client.clone());
+        reader.read_start_line().unwrap();
+        reader.read_headers().unwrap();
+
+        relay(reader, client.clone(), upstream.clone(), None).unwrap();
+
+        // The bytes buffered with the request head are forwarded as well
+        assert_eq!(upstream.output(), b"hello");
+        assert_eq!(client.output(), b"welcome");
+    }
+
+    #[test]
+    fn tunnel_config_only_allows_listed_destinations() {
+        let config = TunnelConfig {
+            allowed: vec!["example.com:443".to_owned()],
+        };
+
+        assert!(config.is_allowed("EXAMPLE.com:443"));
+        assert!(!config.is_allowed("example.com:22"));
+        assert!(!config.is_allowed("internal:443"));
+    }
+}
@@ -0,0 +1,61 @@
+//! End-to-end test of the server over a real TCP connection: accept loop, request parsing,
+//! handler and response writing.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    thread,
+};
+
+use http_lib::{
+    http::{Request, Response, StatusCode},
+    server::{Server, ServerConfig},
+};
+
+fn hello(request: &Request) -> Response {
+    Response::new(StatusCode::Ok).with_body(format!("hello from {}", request.target()))
+}
+
+/// Splits a response into its status line and its body, as framed by `Content-Length`.
+fn parse_response(raw: &[u8]) -> (String, Vec<u8>) {
+    let head_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .expect("response without end of head");
+    let head = std::str::from_utf8(&raw[..head_end]).expect("response head isn't UTF-8");
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap().to_owned();
+    let length: usize = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .map(|(_, value)| value.trim().parse().expect("invalid Content-Length"))
+        .unwrap_or(0);
+
+    let body = raw[head_end + 4..].to_vec();
+    assert_eq!(body.len(), length);
+    (status_line, body)
+}
+
+#[test]
+fn server_answers_request_over_tcp() {
+    // Port 0 lets the OS pick a free port, so concurrent test runs don't collide
+    let server = Server::bind(ServerConfig::with_addr("127.0.0.1:0".parse().unwrap()))
+        .expect("error binding server");
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle().unwrap();
+    let serving = thread::spawn(move || server.serve(hello));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET /round-trip HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw).unwrap();
+
+    let (status_line, body) = parse_response(&raw);
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    assert_eq!(body, b"hello from /round-trip");
+
+    handle.shutdown();
+    serving.join().unwrap().unwrap();
+}